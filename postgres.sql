@@ -20,15 +20,21 @@ CREATE TABLE video (
     state_480p video_upload_state NOT NULL,
     state_720p video_upload_state NOT NULL,
     state_1080p video_upload_state NOT NULL,
-    state_1440p video_upload_state NOT NULL
+    state_1440p video_upload_state NOT NULL,
+    comments_disabled bool NOT NULL DEFAULT false,
+    pinned_comment_id bigint,
+    hidden bool NOT NULL DEFAULT false
 );
 
 CREATE TABLE "like" (
     uuid uuid NOT NULL,
     user_id varchar(32) NOT NULL,
+    timestamp timestamp(6) NOT NULL DEFAULT now(),
     PRIMARY KEY (uuid, user_id)
 );
 
+CREATE INDEX like_user_id ON "like" (user_id, timestamp);
+
 CREATE OR REPLACE FUNCTION add_like_trigger() RETURNS TRIGGER AS $$
 DECLARE
 BEGIN
@@ -46,4 +52,194 @@ END;
 $$ LANGUAGE plpgsql;
 
 CREATE TRIGGER add_like AFTER INSERT ON "like" FOR EACH ROW EXECUTE FUNCTION add_like_trigger();
-CREATE TRIGGER remove_like AFTER DELETE ON "like" FOR EACH ROW EXECUTE FUNCTION remove_like_trigger();
\ No newline at end of file
+CREATE TRIGGER remove_like AFTER DELETE ON "like" FOR EACH ROW EXECUTE FUNCTION remove_like_trigger();
+
+CREATE TABLE comment (
+    id bigserial NOT NULL PRIMARY KEY,
+    uuid uuid NOT NULL REFERENCES video (uuid) ON DELETE CASCADE,
+    user_id varchar(32) NOT NULL,
+    parent_id bigint,
+    body varchar(5000) NOT NULL,
+    timestamp timestamp(6) NOT NULL DEFAULT now(),
+    edit_timestamp timestamp(6) NOT NULL DEFAULT now(),
+    edited bool NOT NULL DEFAULT false,
+    deleted bool NOT NULL DEFAULT false,
+    held bool NOT NULL DEFAULT false,
+    hidden bool NOT NULL DEFAULT false,
+    likes bigint NOT NULL DEFAULT 0,
+    replies bigint NOT NULL DEFAULT 0
+);
+
+CREATE INDEX comment_uuid_parent_id ON comment (uuid, parent_id);
+
+CREATE TABLE comment_like (
+    comment_id bigint NOT NULL REFERENCES comment (id) ON DELETE CASCADE,
+    user_id varchar(32) NOT NULL,
+    PRIMARY KEY (comment_id, user_id)
+);
+
+CREATE OR REPLACE FUNCTION add_comment_trigger() RETURNS TRIGGER AS $$
+DECLARE
+BEGIN
+    IF NOT (NEW.deleted OR NEW.held OR NEW.hidden) THEN
+        UPDATE comment SET replies = replies + 1 WHERE id = NEW.parent_id;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION update_comment_trigger() RETURNS TRIGGER AS $$
+DECLARE
+BEGIN
+    IF (OLD.deleted OR OLD.held OR OLD.hidden) AND NOT (NEW.deleted OR NEW.held OR NEW.hidden) THEN
+        UPDATE comment SET replies = replies + 1 WHERE id = NEW.parent_id;
+    ELSIF NOT (OLD.deleted OR OLD.held OR OLD.hidden) AND (NEW.deleted OR NEW.held OR NEW.hidden) THEN
+        UPDATE comment SET replies = replies - 1 WHERE id = NEW.parent_id;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION remove_comment_trigger() RETURNS TRIGGER AS $$
+DECLARE
+BEGIN
+    IF NOT (OLD.deleted OR OLD.held OR OLD.hidden) THEN
+        UPDATE comment SET replies = replies - 1 WHERE id = OLD.parent_id;
+    END IF;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER add_comment AFTER INSERT ON comment FOR EACH ROW EXECUTE FUNCTION add_comment_trigger();
+CREATE TRIGGER update_comment AFTER UPDATE OF deleted, held, hidden ON comment FOR EACH ROW EXECUTE FUNCTION update_comment_trigger();
+CREATE TRIGGER remove_comment AFTER DELETE ON comment FOR EACH ROW EXECUTE FUNCTION remove_comment_trigger();
+
+CREATE OR REPLACE FUNCTION add_comment_like_trigger() RETURNS TRIGGER AS $$
+DECLARE
+BEGIN
+    UPDATE comment SET likes = likes + 1 WHERE id = NEW.comment_id;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION remove_comment_like_trigger() RETURNS TRIGGER AS $$
+DECLARE
+BEGIN
+    UPDATE comment SET likes = likes - 1 WHERE id = OLD.comment_id;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER add_comment_like AFTER INSERT ON comment_like FOR EACH ROW EXECUTE FUNCTION add_comment_like_trigger();
+CREATE TRIGGER remove_comment_like AFTER DELETE ON comment_like FOR EACH ROW EXECUTE FUNCTION remove_comment_like_trigger();
+
+CREATE TABLE comment_moderation (
+    user_id varchar(32) NOT NULL PRIMARY KEY,
+    banned_words varchar(5000) NOT NULL DEFAULT ''
+);
+
+CREATE TABLE comment_block (
+    user_id varchar(32) NOT NULL,
+    blocked_user_id varchar(32) NOT NULL,
+    PRIMARY KEY (user_id, blocked_user_id)
+);
+
+CREATE TYPE report_target_type AS ENUM ('video', 'comment');
+
+CREATE TABLE report (
+    id bigserial NOT NULL PRIMARY KEY,
+    target_type report_target_type NOT NULL,
+    target_id varchar(36) NOT NULL,
+    reason varchar(1000) NOT NULL,
+    reporter_id varchar(32) NOT NULL,
+    timestamp timestamp(6) NOT NULL DEFAULT now(),
+    resolved bool NOT NULL DEFAULT false,
+    UNIQUE (target_type, target_id, reporter_id)
+);
+
+CREATE INDEX report_resolved ON report (resolved, target_type, target_id);
+
+CREATE TABLE audit_log (
+    id bigserial NOT NULL PRIMARY KEY,
+    user_id varchar(32) NOT NULL,
+    action varchar(32) NOT NULL,
+    target_type report_target_type,
+    target_id varchar(36),
+    details varchar(1000),
+    timestamp timestamp(6) NOT NULL DEFAULT now()
+);
+
+CREATE TABLE channel (
+    user_id varchar(32) NOT NULL PRIMARY KEY,
+    description varchar(5000),
+    banner bytea
+);
+
+CREATE TABLE subscription (
+    user_id varchar(32) NOT NULL,
+    channel_id varchar(32) NOT NULL,
+    timestamp timestamp(6) NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, channel_id)
+);
+
+CREATE INDEX subscription_channel_id ON subscription (channel_id);
+
+CREATE TYPE notification_type AS ENUM ('upload', 'reply', 'like_milestone', 'upload_failed');
+
+CREATE TABLE notification (
+    id bigserial NOT NULL PRIMARY KEY,
+    user_id varchar(32) NOT NULL,
+    notification_type notification_type NOT NULL,
+    actor_id varchar(32),
+    uuid uuid NOT NULL,
+    comment_id bigint,
+    value bigint,
+    timestamp timestamp(6) NOT NULL DEFAULT now(),
+    read bool NOT NULL DEFAULT false,
+    UNIQUE NULLS NOT DISTINCT (user_id, notification_type, uuid, comment_id, value)
+);
+
+CREATE INDEX notification_user_id ON notification (user_id, read, timestamp);
+
+CREATE TYPE playlist_visibility AS ENUM ('public', 'unlisted', 'private');
+
+CREATE TABLE playlist (
+    id uuid NOT NULL PRIMARY KEY,
+    user_id varchar(32) NOT NULL,
+    title varchar(100) NOT NULL,
+    description varchar(5000),
+    visibility playlist_visibility NOT NULL DEFAULT 'private',
+    timestamp timestamp(6) NOT NULL DEFAULT now(),
+    update_timestamp timestamp(6) NOT NULL DEFAULT now(),
+    watch_later bool NOT NULL DEFAULT false
+);
+
+CREATE INDEX playlist_user_id ON playlist (user_id);
+
+CREATE UNIQUE INDEX playlist_watch_later ON playlist (user_id) WHERE watch_later;
+
+CREATE TABLE playlist_item (
+    playlist_id uuid NOT NULL REFERENCES playlist (id) ON DELETE CASCADE,
+    uuid uuid NOT NULL,
+    position integer NOT NULL,
+    timestamp timestamp(6) NOT NULL DEFAULT now(),
+    PRIMARY KEY (playlist_id, uuid)
+);
+
+CREATE INDEX playlist_item_position ON playlist_item (playlist_id, position);
+
+CREATE TABLE watch_history (
+    user_id varchar(32) NOT NULL,
+    uuid uuid NOT NULL,
+    position double precision NOT NULL DEFAULT 0,
+    timestamp timestamp(6) NOT NULL DEFAULT now(),
+    completed bool NOT NULL DEFAULT false,
+    PRIMARY KEY (user_id, uuid)
+);
+
+CREATE INDEX watch_history_timestamp ON watch_history (user_id, timestamp);
+
+CREATE TABLE watch_history_pause (
+    user_id varchar(32) NOT NULL PRIMARY KEY,
+    timestamp timestamp(6) NOT NULL DEFAULT now()
+);
@@ -9,6 +9,7 @@ pub struct Model {
     pub uuid: Uuid,
     #[sea_orm(primary_key, auto_increment = false)]
     pub user_id: String,
+    pub timestamp: DateTime,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,7 +1,18 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
-pub mod prelude;
-
+pub mod audit_log;
+pub mod channel;
+pub mod comment;
+pub mod comment_block;
+pub mod comment_like;
+pub mod comment_moderation;
 pub mod like;
+pub mod notification;
+pub mod playlist;
+pub mod playlist_item;
+pub mod report;
 pub mod sea_orm_active_enums;
+pub mod subscription;
 pub mod video;
+pub mod watch_history;
+pub mod watch_history_pause;
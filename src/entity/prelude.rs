@@ -1,4 +0,0 @@
-//! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
-
-pub use super::like::Entity as Like;
-pub use super::video::Entity as Video;
@@ -1,6 +1,7 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "video_upload_state")]
@@ -12,3 +13,46 @@ pub enum VideoUploadState {
     #[sea_orm(string_value = "uploading")]
     Uploading,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "notification_type")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationType {
+    #[sea_orm(string_value = "like_milestone")]
+    LikeMilestone,
+    #[sea_orm(string_value = "reply")]
+    Reply,
+    #[sea_orm(string_value = "upload")]
+    Upload,
+    #[sea_orm(string_value = "upload_failed")]
+    UploadFailed,
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize,
+)]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "playlist_visibility"
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistVisibility {
+    #[default]
+    #[sea_orm(string_value = "private")]
+    Private,
+    #[sea_orm(string_value = "public")]
+    Public,
+    #[sea_orm(string_value = "unlisted")]
+    Unlisted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "report_target_type")]
+#[serde(rename_all = "lowercase")]
+pub enum ReportTargetType {
+    #[sea_orm(string_value = "comment")]
+    Comment,
+    #[sea_orm(string_value = "video")]
+    Video,
+}
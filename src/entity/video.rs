@@ -26,6 +26,9 @@ pub struct Model {
     pub state_720p: VideoUploadState,
     pub state_1080p: VideoUploadState,
     pub state_1440p: VideoUploadState,
+    pub comments_disabled: bool,
+    pub pinned_comment_id: Option<i64>,
+    pub hidden: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
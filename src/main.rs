@@ -2,16 +2,17 @@ mod entity;
 mod service;
 mod util;
 
-use std::{env, fs::File, io::BufReader};
+use std::{collections::HashSet, env, fs::File, io::BufReader};
 
 use actix_analytics::Analytics;
 use actix_cors::Cors;
 use actix_files::Files;
-use actix_web::{middleware, web::Data, App, HttpServer};
+use actix_web::{cookie::Key, middleware, web::Data, App, HttpServer};
 use actix_web_validator5::JsonConfig;
 use anyhow::anyhow;
 use clerk_rs::{clerk::Clerk, ClerkConfiguration};
 use fred::{
+    clients::SubscriberClient,
     prelude::{ClientLike, RedisClient},
     types::{PerformanceConfig, ReconnectPolicy, RedisConfig},
 };
@@ -25,6 +26,19 @@ use rustls_pemfile::{certs, private_key};
 use sea_orm::{Database, DatabaseConnection};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use tokio::sync::broadcast::{self, Sender};
+use util::{
+    channel::spawn_channel_sync_job,
+    gorse::{GorseApi, GorseBreaker},
+    labels::spawn_labels_job,
+    search::bootstrap_search_indexes,
+    session::{session_middleware, JwksCache},
+    together::forward_together_events,
+    trending::spawn_trending_job,
+    upload::forward_upload_events,
+    visitor::{spawn_visitor_cleanup_job, visitor_middleware},
+};
+use uuid::Uuid;
 pub trait AnyhowResult<T>: Sized {
     fn anyhow(self) -> anyhow::Result<T>;
 }
@@ -49,10 +63,20 @@ pub struct AppState<'a> {
     db_connection: DatabaseConnection,
     redis_client: RedisClient,
     gorse_client: Gorse,
+    gorse_api: GorseApi,
+    gorse_breaker: GorseBreaker,
     meillisearch_client: Client,
     video_index: Index,
+    playlist_index: Index,
+    channel_index: Index,
     handlebars: Handlebars<'a>,
     clerk: Clerk,
+    jwks: JwksCache,
+    admin_user_ids: HashSet<String>,
+    upload_events: Sender<(String, String)>,
+    together_events: Sender<(Uuid, Option<Uuid>, String)>,
+    ice_servers: Vec<String>,
+    visitor_key: Key,
 }
 
 #[tokio::main(flavor = "multi_thread")]
@@ -80,11 +104,39 @@ async fn main() -> anyhow::Result<()> {
     let meillisearch_api_key =
         env::var("MEILLISEARCH_API_KEY").expect("GORSE_API_KEY is not set in .env file");
 
+    let admin_user_ids = env::var("ADMIN_USER_IDS")
+        .expect("ADMIN_USER_IDS is not set in .env file")
+        .split(",")
+        .map(str::trim)
+        .filter(|user_id| !user_id.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    let ice_servers = env::var("WEBRTC_ICE_SERVERS")
+        .expect("WEBRTC_ICE_SERVERS is not set in .env file")
+        .split(",")
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    let visitor_key = Key::try_from(
+        env::var("VISITOR_COOKIE_KEY")
+            .expect("VISITOR_COOKIE_KEY is not set in .env file")
+            .as_bytes(),
+    )?;
+
     let db_connection = Database::connect(db_url).await?;
     let redis_config = RedisConfig::from_url(&redis_url)?;
     let redis_performance = PerformanceConfig::default();
     let redis_policy = ReconnectPolicy::default();
     let redis_client = RedisClient::new(
+        redis_config.clone(),
+        Some(redis_performance.clone()),
+        None,
+        Some(redis_policy.clone()),
+    );
+    let redis_subscriber_client = SubscriberClient::new(
         redis_config,
         Some(redis_performance),
         None,
@@ -93,10 +145,22 @@ async fn main() -> anyhow::Result<()> {
 
     redis_client.connect();
     redis_client.wait_for_connect().await?;
+    redis_subscriber_client.connect();
+    redis_subscriber_client.wait_for_connect().await?;
+
+    let (upload_events, _) = broadcast::channel(1024);
+    let (together_events, _) = broadcast::channel(1024);
 
+    redis_subscriber_client.manage_subscriptions();
+    forward_upload_events(redis_subscriber_client.clone(), upload_events.clone()).await?;
+    forward_together_events(redis_subscriber_client, together_events.clone()).await?;
+
+    let gorse_api = GorseApi::new(gorse_url.clone(), gorse_api_key.clone());
     let gorse_client = Gorse::new(gorse_url, gorse_api_key);
     let meillisearch_client = Client::new(meillisearch_url, Some(meillisearch_api_key))?;
 
+    bootstrap_search_indexes(&meillisearch_client).await?;
+
     let mut handlebars = Handlebars::new();
 
     handlebars.register_templates_directory(
@@ -142,12 +206,27 @@ async fn main() -> anyhow::Result<()> {
         db_connection,
         redis_client,
         gorse_client,
+        gorse_api,
+        gorse_breaker: GorseBreaker::default(),
         video_index: meillisearch_client.index("video"),
+        playlist_index: meillisearch_client.index("playlist"),
+        channel_index: meillisearch_client.index("channel"),
         meillisearch_client,
         handlebars,
         clerk,
+        jwks: JwksCache::default(),
+        admin_user_ids,
+        upload_events,
+        together_events,
+        ice_servers,
+        visitor_key,
     });
 
+    spawn_channel_sync_job(state.clone());
+    spawn_labels_job(state.clone());
+    spawn_trending_job(state.clone());
+    spawn_visitor_cleanup_job(state.clone());
+
     HttpServer::new(move || {
         let cors = Cors::default().allow_any_origin();
 
@@ -156,19 +235,74 @@ async fn main() -> anyhow::Result<()> {
             .app_data(JsonConfig::default().limit(131072))
             .wrap(Analytics::new(analytics_api_key.clone()))
             .wrap(cors)
+            .wrap_fn(visitor_middleware)
+            .wrap_fn(session_middleware)
             .wrap(middleware::DefaultHeaders::new().add(("Cache-Control", "max-age=31536000")))
             .wrap(middleware::NormalizePath::trim())
             .wrap(middleware::Compress::default())
+            .service(service::admin::get)
+            .service(service::admin::reindex::get)
+            .service(service::admin::reindex::post)
+            .service(service::admin::video::uuid::hide::post)
+            .service(service::admin::video::uuid::hide::delete)
+            .service(service::admin::comment::id::hide::post)
+            .service(service::admin::comment::id::hide::delete)
+            .service(service::admin::report::id::delete)
+            .service(service::channel::put)
+            .service(service::channel::user_id::get)
+            .service(service::channel::user_id::banner::get)
+            .service(service::comment::put)
+            .service(service::comment::uuid::get)
+            .service(service::comment::id::patch)
+            .service(service::comment::id::delete)
+            .service(service::comment::id::like::post)
+            .service(service::comment::id::like::delete)
+            .service(service::comment::id::pin::post)
+            .service(service::comment::id::pin::delete)
+            .service(service::comment::id::approve::post)
+            .service(service::feed::subscriptions::get)
+            .service(service::history::get)
+            .service(service::history::delete)
+            .service(service::history::pause::put)
+            .service(service::history::uuid::post)
+            .service(service::history::uuid::delete)
             .service(service::index::get)
             .service(service::like::uuid::post)
             .service(service::like::uuid::delete)
+            .service(service::moderation::get)
+            .service(service::moderation::banned_words::put)
+            .service(service::moderation::block::user_id::put)
+            .service(service::moderation::block::user_id::delete)
+            .service(service::moderation::uuid::patch)
+            .service(service::notification::get)
+            .service(service::notification::unread::get)
+            .service(service::notification::read::post)
+            .service(service::playlist::get)
+            .service(service::playlist::put)
+            .service(service::playlist::watch_later::get)
+            .service(service::playlist::liked::get)
+            .service(service::playlist::id::get)
+            .service(service::playlist::id::patch)
+            .service(service::playlist::id::delete)
+            .service(service::playlist::id::order::put)
+            .service(service::playlist::id::video::uuid::put)
+            .service(service::playlist::id::video::uuid::delete)
+            .service(service::report::post)
             .service(service::results::get)
+            .service(service::results::suggestions::get)
+            .service(service::session::sid::delete)
             .service(service::share::uuid::post)
+            .service(service::subscription::channel_id::post)
+            .service(service::subscription::channel_id::delete)
             .service(service::thumbnail::uuid::get)
+            .service(service::together::post)
             .service(service::together::uuid::get)
+            .service(service::together::uuid::ws::get)
+            .service(service::trending::get)
             .service(service::upload::get)
             .service(service::upload::put)
             .service(service::upload::delete)
+            .service(service::upload::events::get)
             .service(service::upload::uuid::resolution::post)
             .service(service::video::uuid::resolution::get)
             .service(service::video::uuid::resolution::start_timestamp::end_timestamp::get)
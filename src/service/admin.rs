@@ -0,0 +1,470 @@
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use ::uuid::Uuid;
+use actix_web::{
+    delete, error::ErrorInternalServerError, get, post, web::Data, HttpRequest, HttpResponse,
+    Responder,
+};
+use actix_web_validator5::Path;
+use fred::{
+    interfaces::KeysInterface,
+    types::{Expiration, SetOptions},
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    entity::{
+        audit_log, comment as comment_entity, report as report_entity,
+        sea_orm_active_enums::ReportTargetType, video as video_entity,
+    },
+    util::{
+        channel::{channel_info_or_unknown, get_channels_info},
+        comment::find_comment,
+        get_authentication_data,
+        reindex::{get_reindex_status, start_reindex, REINDEX_LOCK_KEY, REINDEX_LOCK_TIMEOUT},
+        report::{
+            is_admin, log_action, resolve_reports, set_comment_hidden, set_video_hidden,
+            sync_video_hidden,
+        },
+        video::find_video,
+    },
+    AppState,
+};
+
+const AUDIT_LOG_PAGE_SIZE: u64 = 50;
+
+fn excerpt(text: &str) -> String {
+    text.chars().take(1000).collect()
+}
+
+#[get("/admin")]
+async fn get(request: HttpRequest, data: Data<AppState<'_>>) -> actix_web::Result<impl Responder> {
+    let Some(jwt) = get_authentication_data(&request, &data).await else {
+        return Ok(HttpResponse::TemporaryRedirect()
+            .insert_header(("Cache-Control", "no-store"))
+            .insert_header(("Location", "/"))
+            .finish());
+    };
+
+    if !is_admin(&jwt, &data) {
+        return Ok(HttpResponse::TemporaryRedirect()
+            .insert_header(("Cache-Control", "no-store"))
+            .insert_header(("Location", "/"))
+            .finish());
+    }
+
+    let reports = report_entity::Entity::find()
+        .filter(report_entity::Column::Resolved.eq(false))
+        .order_by_asc(report_entity::Column::TargetType)
+        .order_by_asc(report_entity::Column::TargetId)
+        .order_by_desc(report_entity::Column::Timestamp)
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find reports"))?;
+    let audit_logs = audit_log::Entity::find()
+        .order_by_desc(audit_log::Column::Timestamp)
+        .limit(AUDIT_LOG_PAGE_SIZE)
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find the audit log"))?;
+    let video_uuids: HashSet<Uuid> = reports
+        .iter()
+        .filter(|report| report.target_type == ReportTargetType::Video)
+        .filter_map(|report| Uuid::from_str(&report.target_id).ok())
+        .collect();
+    let comment_ids: HashSet<i64> = reports
+        .iter()
+        .filter(|report| report.target_type == ReportTargetType::Comment)
+        .filter_map(|report| report.target_id.parse().ok())
+        .collect();
+    let videos: HashMap<String, video_entity::Model> = video_entity::Entity::find()
+        .filter(
+            video_entity::Column::Uuid
+                .is_in(video_uuids)
+                .or(video_entity::Column::Hidden.eq(true)),
+        )
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find a videos"))?
+        .into_iter()
+        .map(|video| (video.uuid.to_string(), video))
+        .collect();
+    let comments: HashMap<String, comment_entity::Model> = comment_entity::Entity::find()
+        .filter(
+            comment_entity::Column::Id
+                .is_in(comment_ids)
+                .or(comment_entity::Column::Hidden.eq(true)),
+        )
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find comments"))?
+        .into_iter()
+        .map(|comment| (comment.id.to_string(), comment))
+        .collect();
+    let mut user_ids: HashSet<String> = reports
+        .iter()
+        .map(|report| report.reporter_id.clone())
+        .collect();
+
+    user_ids.extend(audit_logs.iter().map(|audit_log| audit_log.user_id.clone()));
+    user_ids.extend(videos.values().map(|video| video.user_id.clone()));
+    user_ids.extend(comments.values().map(|comment| comment.user_id.clone()));
+
+    let channels_info = get_channels_info(&user_ids, &data.clerk, &data.redis_client).await;
+    let target_to_json = |target_type: &ReportTargetType, target_id: &String| match target_type {
+        ReportTargetType::Video => videos.get(target_id).map(|video| {
+            json!({
+                "uuid": video.uuid,
+                "title": video.title,
+                "hidden": video.hidden,
+                "channel_info": channel_info_or_unknown(&channels_info, &video.user_id),
+            })
+        }),
+        ReportTargetType::Comment => comments.get(target_id).map(|comment| {
+            json!({
+                "uuid": comment.uuid,
+                "body": comment.body,
+                "hidden": comment.hidden,
+                "channel_info": channel_info_or_unknown(&channels_info, &comment.user_id),
+            })
+        }),
+    };
+    let reports: Vec<Value> = reports
+        .iter()
+        .map(|report| {
+            json!({
+                "id": report.id,
+                "target_type": report.target_type,
+                "target_id": report.target_id,
+                "target": target_to_json(&report.target_type, &report.target_id),
+                "reason": report.reason,
+                "timestamp": report.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                "channel_info": channel_info_or_unknown(&channels_info, &report.reporter_id),
+            })
+        })
+        .collect();
+    let hidden_videos: Vec<Value> = videos
+        .iter()
+        .filter(|(_, video)| video.hidden)
+        .filter_map(|(target_id, _)| target_to_json(&ReportTargetType::Video, target_id))
+        .collect();
+    let hidden_comments: Vec<Value> = comments
+        .iter()
+        .filter(|(_, comment)| comment.hidden)
+        .map(|(target_id, _)| {
+            json!({
+                "id": target_id,
+                "target": target_to_json(&ReportTargetType::Comment, target_id),
+            })
+        })
+        .collect();
+    let audit_logs: Vec<Value> = audit_logs
+        .iter()
+        .map(|audit_log| {
+            json!({
+                "action": audit_log.action,
+                "target_type": audit_log.target_type,
+                "target_id": audit_log.target_id,
+                "details": audit_log.details,
+                "timestamp": audit_log.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                "channel_info": channel_info_or_unknown(&channels_info, &audit_log.user_id),
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .insert_header(("Content-type", "text/html; charset=utf-8"))
+        .body(
+            data.handlebars
+                .render(
+                    "admin",
+                    &json!({
+                        "reports": reports,
+                        "hidden_videos": hidden_videos,
+                        "hidden_comments": hidden_comments,
+                        "audit_logs": audit_logs,
+                    }),
+                )
+                .unwrap(),
+        ))
+}
+
+pub mod reindex {
+    use super::*;
+
+    #[post("/admin/reindex")]
+    async fn post(
+        request: HttpRequest,
+        data: Data<AppState<'static>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        if !is_admin(&jwt, &data) {
+            return Ok(HttpResponse::Forbidden().body("Admin access required"));
+        }
+
+        let locked = data
+            .redis_client
+            .set::<Option<String>, _, _>(
+                REINDEX_LOCK_KEY,
+                &jwt.sub,
+                Some(Expiration::EX(REINDEX_LOCK_TIMEOUT)),
+                Some(SetOptions::NX),
+                false,
+            )
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to start the reindex"))?
+            .is_some();
+
+        if !locked {
+            return Ok(HttpResponse::Conflict().body("A reindex is already running"));
+        }
+
+        start_reindex(jwt.sub, data).await;
+
+        Ok(HttpResponse::Accepted().finish())
+    }
+
+    /// Lists what the running or last reindex changed, the ids included.
+    #[get("/admin/reindex")]
+    async fn get(
+        request: HttpRequest,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        if !is_admin(&jwt, &data) {
+            return Ok(HttpResponse::Forbidden().body("Admin access required"));
+        }
+
+        let Some(status) = get_reindex_status(&data).await else {
+            return Ok(HttpResponse::NotFound().body("No reindex has run yet"));
+        };
+
+        Ok(HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .content_type("application/json")
+            .body(status))
+    }
+}
+
+pub mod video {
+    use super::*;
+
+    pub mod uuid {
+        use super::*;
+
+        #[derive(Deserialize, Validate, Debug)]
+        struct HideVideo {
+            uuid: Uuid,
+        }
+
+        pub mod hide {
+            use super::*;
+
+            async fn hide(
+                request: HttpRequest,
+                params: Path<HideVideo>,
+                hidden: bool,
+                data: Data<AppState<'_>>,
+            ) -> actix_web::Result<HttpResponse> {
+                let Some(jwt) = get_authentication_data(&request, &data).await else {
+                    return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+                };
+
+                if !is_admin(&jwt, &data) {
+                    return Ok(HttpResponse::Forbidden().body("Admin access required"));
+                }
+
+                let video = find_video(&params.uuid, &data.db_connection).await?;
+                // an unchanged flag still goes through, so a retry finishes a failed sync
+                let video = match video.hidden == hidden {
+                    true => video,
+                    false => set_video_hidden(video, hidden, &data).await?,
+                };
+                let target_id = video.uuid.to_string();
+
+                if hidden {
+                    resolve_reports(ReportTargetType::Video, &target_id, &data).await?;
+                }
+
+                log_action(
+                    &jwt.sub,
+                    if hidden { "hide" } else { "restore" },
+                    Some((ReportTargetType::Video, &target_id)),
+                    Some(video.title.clone()),
+                    &data,
+                )
+                .await?;
+                sync_video_hidden(&video, &data).await?;
+
+                Ok(HttpResponse::Ok().finish())
+            }
+
+            #[post("/admin/video/{uuid}/hide")]
+            async fn post(
+                request: HttpRequest,
+                params: Path<HideVideo>,
+                data: Data<AppState<'_>>,
+            ) -> actix_web::Result<impl Responder> {
+                hide(request, params, true, data).await
+            }
+
+            #[delete("/admin/video/{uuid}/hide")]
+            async fn delete(
+                request: HttpRequest,
+                params: Path<HideVideo>,
+                data: Data<AppState<'_>>,
+            ) -> actix_web::Result<impl Responder> {
+                hide(request, params, false, data).await
+            }
+        }
+    }
+}
+
+pub mod comment {
+    use super::*;
+
+    pub mod id {
+        use super::*;
+
+        #[derive(Deserialize, Validate, Debug)]
+        struct HideComment {
+            id: i64,
+        }
+
+        pub mod hide {
+            use super::*;
+
+            async fn hide(
+                request: HttpRequest,
+                params: Path<HideComment>,
+                hidden: bool,
+                data: Data<AppState<'_>>,
+            ) -> actix_web::Result<HttpResponse> {
+                let Some(jwt) = get_authentication_data(&request, &data).await else {
+                    return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+                };
+
+                if !is_admin(&jwt, &data) {
+                    return Ok(HttpResponse::Forbidden().body("Admin access required"));
+                }
+
+                let comment = find_comment(params.id, &data.db_connection).await?;
+
+                if comment.hidden == hidden {
+                    return Ok(HttpResponse::Ok().finish());
+                }
+
+                let comment = set_comment_hidden(comment, hidden, &data).await?;
+                let target_id = comment.id.to_string();
+
+                if hidden {
+                    resolve_reports(ReportTargetType::Comment, &target_id, &data).await?;
+                }
+
+                log_action(
+                    &jwt.sub,
+                    if hidden { "hide" } else { "restore" },
+                    Some((ReportTargetType::Comment, &target_id)),
+                    Some(excerpt(&comment.body)),
+                    &data,
+                )
+                .await?;
+
+                Ok(HttpResponse::Ok().finish())
+            }
+
+            #[post("/admin/comment/{id}/hide")]
+            async fn post(
+                request: HttpRequest,
+                params: Path<HideComment>,
+                data: Data<AppState<'_>>,
+            ) -> actix_web::Result<impl Responder> {
+                hide(request, params, true, data).await
+            }
+
+            #[delete("/admin/comment/{id}/hide")]
+            async fn delete(
+                request: HttpRequest,
+                params: Path<HideComment>,
+                data: Data<AppState<'_>>,
+            ) -> actix_web::Result<impl Responder> {
+                hide(request, params, false, data).await
+            }
+        }
+    }
+}
+
+pub mod report {
+    use super::*;
+
+    pub mod id {
+        use super::*;
+
+        #[derive(Deserialize, Validate, Debug)]
+        struct DismissReport {
+            id: i64,
+        }
+
+        #[delete("/admin/report/{id}")]
+        async fn delete(
+            request: HttpRequest,
+            params: Path<DismissReport>,
+            data: Data<AppState<'_>>,
+        ) -> actix_web::Result<impl Responder> {
+            let Some(jwt) = get_authentication_data(&request, &data).await else {
+                return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+            };
+
+            if !is_admin(&jwt, &data) {
+                return Ok(HttpResponse::Forbidden().body("Admin access required"));
+            }
+
+            let Some(report) = report_entity::Entity::find_by_id(params.id)
+                .one(&data.db_connection)
+                .await
+                .map_err(|_| ErrorInternalServerError("Unable to find a report with this id"))?
+            else {
+                return Ok(HttpResponse::NotFound().body("Unable to find a report with this id"));
+            };
+
+            if report.resolved {
+                return Ok(HttpResponse::Ok().finish());
+            }
+
+            let mut report = report_entity::ActiveModel::from(report);
+
+            report.resolved = Set(true);
+
+            let report = report
+                .update(&data.db_connection)
+                .await
+                .map_err(|_| ErrorInternalServerError("Unable to dismiss report"))?;
+
+            log_action(
+                &jwt.sub,
+                "dismiss",
+                Some((report.target_type, &report.target_id)),
+                Some(excerpt(&report.reason)),
+                &data,
+            )
+            .await?;
+
+            Ok(HttpResponse::Ok().finish())
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use actix_web::{
+    error::{ErrorInternalServerError, ErrorNotFound},
+    get, put,
+    web::Data,
+    HttpRequest, HttpResponse, Responder,
+};
+use actix_web_validator5::{Json, Path, Query};
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    EntityTrait, QueryOrder, QuerySelect, Set,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    entity::{channel, video},
+    util::{
+        channel::{get_channel_info, update_channel_index},
+        decode_webp_data_url, get_authentication_data,
+        subscription::{count_subscribers, is_subscribed},
+        video::find_public_channel_videos,
+    },
+    AppState,
+};
+
+const CHANNEL_PAGE_SIZE: u64 = 24;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum ChannelSort {
+    #[default]
+    Newest,
+    Views,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+struct PutChannel {
+    #[validate(length(min = 0, max = 5000))]
+    description: Option<String>,
+    banner: Option<String>,
+}
+
+#[put("/channel")]
+async fn put(
+    request: HttpRequest,
+    payload: Json<PutChannel>,
+    data: Data<AppState<'_>>,
+) -> actix_web::Result<impl Responder> {
+    let Some(jwt) = get_authentication_data(&request, &data).await else {
+        return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+    };
+
+    let mut update_columns = vec![channel::Column::Description];
+    let mut channel = channel::ActiveModel {
+        user_id: Set(jwt.sub.clone()),
+        description: Set(payload
+            .description
+            .as_deref()
+            .map(str::trim)
+            .filter(|description| !description.is_empty())
+            .map(str::to_string)),
+        ..Default::default()
+    };
+
+    // the banner is left untouched when not sent, and removed when empty
+    if let Some(banner) = &payload.banner {
+        update_columns.push(channel::Column::Banner);
+        channel.banner = Set(match banner.is_empty() {
+            true => None,
+            false => Some(decode_webp_data_url(banner)?),
+        });
+    }
+
+    channel::Entity::insert(channel)
+        .on_conflict(
+            OnConflict::column(channel::Column::UserId)
+                .update_columns(update_columns)
+                .to_owned(),
+        )
+        .exec_without_returning(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to update the channel"))?;
+    update_channel_index(&jwt.sub, &data).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub mod user_id {
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct GetChannel {
+        #[validate(length(min = 1, max = 32))]
+        user_id: String,
+    }
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct GetChannelQuery {
+        sort: Option<ChannelSort>,
+        #[validate(range(max = 1000))]
+        page: Option<u64>,
+    }
+
+    #[get("/channel/{user_id}")]
+    async fn get(
+        request: HttpRequest,
+        params: Path<GetChannel>,
+        query: Query<GetChannelQuery>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let jwt = get_authentication_data(&request, &data).await;
+        let channel_info =
+            get_channel_info(&params.user_id, &data.clerk, &data.redis_client).await?;
+        let (description, banner) = channel::Entity::find_by_id(&params.user_id)
+            .select_only()
+            .column(channel::Column::Description)
+            .column_as(Expr::col(channel::Column::Banner).is_not_null(), "banner")
+            .into_tuple::<(Option<String>, bool)>()
+            .one(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to find the channel"))?
+            .unwrap_or_default();
+        let (total_views, video_count) = find_public_channel_videos(&params.user_id)
+            .select_only()
+            .column_as(
+                Expr::cust("CAST(COALESCE(SUM(views), 0) AS bigint)"),
+                "total_views",
+            )
+            .column_as(Expr::col(video::Column::Uuid).count(), "video_count")
+            .into_tuple::<(i64, i64)>()
+            .one(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to count the channel videos"))?
+            .unwrap_or_default();
+        let subscribers = count_subscribers(&params.user_id, &data.db_connection).await?;
+        let subscribed = match &jwt {
+            Some(jwt) => is_subscribed(&jwt.sub, &params.user_id, &data.db_connection).await,
+            None => false,
+        };
+        let sort = query.sort.unwrap_or_default();
+        let page = query.page.unwrap_or_default();
+        let select = find_public_channel_videos(&params.user_id);
+        let select = match sort {
+            ChannelSort::Newest => select.order_by_desc(video::Column::Timestamp),
+            ChannelSort::Views => select
+                .order_by_desc(video::Column::Views)
+                .order_by_desc(video::Column::Timestamp),
+        };
+        let videos: Vec<Value> = select
+            .offset(page * CHANNEL_PAGE_SIZE)
+            .limit(CHANNEL_PAGE_SIZE)
+            .all(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to find a videos"))?
+            .into_iter()
+            .map(|video| {
+                json!({
+                    "uuid": video.uuid,
+                    "title": video.title,
+                    "views": video.views,
+                    "timestamp": video.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                    "duration": video.duration,
+                    "channel_info": channel_info,
+                })
+            })
+            .collect();
+
+        Ok(HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .insert_header(("Content-type", "text/html; charset=utf-8"))
+            .body(
+                data.handlebars
+                    .render(
+                        "channel",
+                        &json!({
+                            "channel_info": channel_info,
+                            "description": description,
+                            "banner": banner,
+                            "total_views": total_views,
+                            "video_count": video_count,
+                            "subscribers": subscribers,
+                            "subscribed": subscribed,
+                            "videos": videos,
+                            "sort": sort,
+                            "page": page,
+                            "has_previous_page": page > 0,
+                            "has_next_page": (page + 1) * CHANNEL_PAGE_SIZE < video_count as u64,
+                            "previous_page": page.saturating_sub(1),
+                            "next_page": page + 1,
+                            "owner": jwt.is_some_and(|jwt| jwt.sub == params.user_id),
+                        }),
+                    )
+                    .unwrap(),
+            ))
+    }
+
+    pub mod banner {
+        use super::*;
+
+        #[get("/channel/{user_id}/banner")]
+        async fn get(
+            params: Path<GetChannel>,
+            data: Data<AppState<'_>>,
+        ) -> actix_web::Result<impl Responder> {
+            let banner = channel::Entity::find_by_id(&params.user_id)
+                .select_only()
+                .column(channel::Column::Banner)
+                .into_tuple::<Option<Vec<u8>>>()
+                .one(&data.db_connection)
+                .await
+                .map_err(|_| ErrorInternalServerError("Unable to find the channel"))?
+                .flatten()
+                .ok_or_else(|| ErrorNotFound("This channel has no banner"))?;
+
+            Ok(HttpResponse::Ok()
+                .insert_header(("Cache-Control", "no-cache"))
+                .insert_header(("Content-Type", "image/webp"))
+                .body(banner))
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use ::uuid::Uuid;
+use actix_web::{
+    delete,
+    error::{ErrorBadRequest, ErrorForbidden, ErrorInternalServerError},
+    get, patch, post, put,
+    web::Data,
+    HttpRequest, HttpResponse, Responder,
+};
+use actix_web_validator5::{Json, Path, Query};
+use chrono::Utc;
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set,
+};
+use serde::Deserialize;
+use serde_json::json;
+use validator::Validate;
+
+use crate::{
+    entity::{comment, comment_like, video},
+    util::{
+        channel::get_channel_info,
+        comment::{check_comment, comment_to_json, find_comment, get_comments, CommentSort},
+        get_authentication_data,
+        notification::notify_reply,
+        video::{find_video, find_visible_video},
+    },
+    AppState,
+};
+
+#[derive(Deserialize, Validate, Debug)]
+struct PutComment {
+    uuid: Uuid,
+    parent_id: Option<i64>,
+    #[validate(length(min = 1, max = 5000))]
+    body: String,
+}
+
+#[put("/comment")]
+async fn put(
+    request: HttpRequest,
+    payload: Json<PutComment>,
+    data: Data<AppState<'_>>,
+) -> actix_web::Result<impl Responder> {
+    let Some(jwt) = get_authentication_data(&request, &data).await else {
+        return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+    };
+
+    let body = payload.body.trim();
+
+    if body.is_empty() {
+        return Err(ErrorBadRequest("The comment is empty"));
+    }
+
+    let video = find_visible_video(&payload.uuid, &data.db_connection).await?;
+    let held = check_comment(&video, &jwt.sub, body, &data.db_connection).await?;
+    let parent = match payload.parent_id {
+        Some(parent_id) => {
+            let parent = find_comment(parent_id, &data.db_connection).await?;
+
+            if parent.uuid != payload.uuid || parent.deleted || parent.hidden {
+                return Err(ErrorBadRequest("Unable to reply to this comment"));
+            }
+
+            Some(parent)
+        }
+        None => None,
+    };
+    // replies to a reply are attached to the top level comment
+    let parent_id = parent
+        .as_ref()
+        .map(|parent| parent.parent_id.unwrap_or(parent.id));
+    let comment = comment::ActiveModel {
+        uuid: Set(payload.uuid),
+        user_id: Set(jwt.sub.clone()),
+        parent_id: Set(parent_id),
+        body: Set(body.to_string()),
+        held: Set(held),
+        ..Default::default()
+    }
+    .insert(&data.db_connection)
+    .await
+    .map_err(|_| ErrorInternalServerError("Unable to add comment"))?;
+
+    if let Some(parent) = &parent {
+        notify_reply(parent, &comment, &data).await.ok();
+    }
+
+    let channel_info = get_channel_info(&jwt.sub, &data.clerk, &data.redis_client).await?;
+
+    Ok(HttpResponse::Ok().json(comment_to_json(&comment, &channel_info, false, false)))
+}
+
+pub mod uuid {
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct GetComment {
+        uuid: Uuid,
+    }
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct GetCommentQuery {
+        parent_id: Option<i64>,
+        sort: Option<CommentSort>,
+        #[validate(range(max = 1000))]
+        page: Option<u64>,
+    }
+
+    #[get("/comment/{uuid}")]
+    async fn get(
+        request: HttpRequest,
+        params: Path<GetComment>,
+        query: Query<GetCommentQuery>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let jwt = get_authentication_data(&request, &data).await;
+        let video = find_visible_video(&params.uuid, &data.db_connection).await?;
+        let comments = get_comments(
+            &video,
+            query.parent_id,
+            query.sort.unwrap_or_default(),
+            query.page.unwrap_or_default(),
+            jwt.as_ref().map(|jwt| jwt.sub.as_str()),
+            &data,
+        )
+        .await?;
+
+        Ok(HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .json(comments))
+    }
+}
+
+pub mod id {
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct CommentId {
+        id: i64,
+    }
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct PatchComment {
+        #[validate(length(min = 1, max = 5000))]
+        body: String,
+    }
+
+    #[patch("/comment/{id}")]
+    async fn patch(
+        request: HttpRequest,
+        params: Path<CommentId>,
+        payload: Json<PatchComment>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        let body = payload.body.trim();
+
+        if body.is_empty() {
+            return Err(ErrorBadRequest("The comment is empty"));
+        }
+
+        let comment = find_comment(params.id, &data.db_connection).await?;
+
+        if comment.user_id != jwt.sub {
+            return Ok(HttpResponse::Forbidden().body("You cannot edit another user's comment"));
+        }
+
+        if comment.deleted || comment.hidden {
+            return Err(ErrorBadRequest("Unable to edit a deleted comment"));
+        }
+
+        let video = find_video(&comment.uuid, &data.db_connection).await?;
+        let held = check_comment(&video, &jwt.sub, body, &data.db_connection).await?;
+        let mut comment = comment::ActiveModel::from(comment);
+
+        comment.body = Set(body.to_string());
+        comment.held = Set(held);
+        comment.edited = Set(true);
+        comment.edit_timestamp = Set(Utc::now().naive_utc());
+
+        let comment = comment
+            .update(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to edit comment"))?;
+        let liked = comment_like::Entity::find_by_id((comment.id, jwt.sub.clone()))
+            .one(&data.db_connection)
+            .await
+            .map(|like| like.is_some())
+            .unwrap_or(false);
+        let channel_info = get_channel_info(&jwt.sub, &data.clerk, &data.redis_client).await?;
+
+        Ok(HttpResponse::Ok().json(comment_to_json(
+            &comment,
+            &channel_info,
+            liked,
+            video.pinned_comment_id == Some(comment.id),
+        )))
+    }
+
+    #[delete("/comment/{id}")]
+    async fn delete(
+        request: HttpRequest,
+        params: Path<CommentId>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        let comment = find_comment(params.id, &data.db_connection).await?;
+        let video = find_video(&comment.uuid, &data.db_connection).await?;
+
+        // the owner of the video can delete any comment posted on it
+        if comment.user_id != jwt.sub && video.user_id != jwt.sub {
+            return Ok(HttpResponse::Forbidden().body("You cannot delete another user's comment"));
+        }
+
+        if video.pinned_comment_id == Some(comment.id) {
+            set_pinned_comment(video, None, &data).await?;
+        }
+
+        let mut comment = comment::ActiveModel::from(comment);
+
+        comment.deleted = Set(true);
+        comment
+            .update(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to delete comment"))?;
+
+        Ok(HttpResponse::Ok().finish())
+    }
+
+    async fn find_owned_comment(
+        id: i64,
+        user_id: &str,
+        data: &AppState<'_>,
+    ) -> actix_web::Result<(comment::Model, video::Model)> {
+        let comment = find_comment(id, &data.db_connection).await?;
+        let video = find_video(&comment.uuid, &data.db_connection).await?;
+
+        if video.user_id != user_id {
+            return Err(ErrorForbidden(
+                "You cannot moderate comments on another user's video",
+            ));
+        }
+
+        Ok((comment, video))
+    }
+
+    async fn set_pinned_comment(
+        video: video::Model,
+        pinned_comment_id: Option<i64>,
+        data: &AppState<'_>,
+    ) -> actix_web::Result<()> {
+        let mut video = video::ActiveModel::from(video);
+
+        video.pinned_comment_id = Set(pinned_comment_id);
+        video
+            .update(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to pin comment"))?;
+
+        Ok(())
+    }
+
+    pub mod pin {
+        use super::*;
+
+        #[post("/comment/{id}/pin")]
+        async fn post(
+            request: HttpRequest,
+            params: Path<CommentId>,
+            data: Data<AppState<'_>>,
+        ) -> actix_web::Result<impl Responder> {
+            let Some(jwt) = get_authentication_data(&request, &data).await else {
+                return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+            };
+
+            let (comment, video) = find_owned_comment(params.id, &jwt.sub, &data).await?;
+
+            if comment.parent_id.is_some() || comment.deleted || comment.hidden || comment.held {
+                return Err(ErrorBadRequest("Unable to pin this comment"));
+            }
+
+            set_pinned_comment(video, Some(comment.id), &data).await?;
+
+            Ok(HttpResponse::Ok().finish())
+        }
+
+        #[delete("/comment/{id}/pin")]
+        async fn delete(
+            request: HttpRequest,
+            params: Path<CommentId>,
+            data: Data<AppState<'_>>,
+        ) -> actix_web::Result<impl Responder> {
+            let Some(jwt) = get_authentication_data(&request, &data).await else {
+                return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+            };
+
+            let (comment, video) = find_owned_comment(params.id, &jwt.sub, &data).await?;
+
+            if video.pinned_comment_id == Some(comment.id) {
+                set_pinned_comment(video, None, &data).await?;
+            }
+
+            Ok(HttpResponse::Ok().finish())
+        }
+    }
+
+    pub mod approve {
+        use super::*;
+
+        #[post("/comment/{id}/approve")]
+        async fn post(
+            request: HttpRequest,
+            params: Path<CommentId>,
+            data: Data<AppState<'_>>,
+        ) -> actix_web::Result<impl Responder> {
+            let Some(jwt) = get_authentication_data(&request, &data).await else {
+                return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+            };
+
+            let (comment, _) = find_owned_comment(params.id, &jwt.sub, &data).await?;
+            let mut comment = comment::ActiveModel::from(comment);
+
+            comment.held = Set(false);
+            comment
+                .update(&data.db_connection)
+                .await
+                .map_err(|_| ErrorInternalServerError("Unable to approve comment"))?;
+
+            Ok(HttpResponse::Ok().finish())
+        }
+    }
+
+    pub mod like {
+        use super::*;
+
+        #[post("/comment/{id}/like")]
+        async fn post(
+            request: HttpRequest,
+            params: Path<CommentId>,
+            data: Data<AppState<'_>>,
+        ) -> actix_web::Result<impl Responder> {
+            let Some(jwt) = get_authentication_data(&request, &data).await else {
+                return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+            };
+
+            find_comment(params.id, &data.db_connection).await?;
+            comment_like::Entity::insert(comment_like::ActiveModel {
+                comment_id: Set(params.id),
+                user_id: Set(jwt.sub),
+            })
+            .on_conflict(
+                OnConflict::columns([
+                    comment_like::Column::CommentId,
+                    comment_like::Column::UserId,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to add like"))?;
+
+            let comment = find_comment(params.id, &data.db_connection).await?;
+
+            Ok(HttpResponse::Ok().json(json!({
+                "likes": comment.likes,
+                "liked": true,
+            })))
+        }
+
+        #[delete("/comment/{id}/like")]
+        async fn delete(
+            request: HttpRequest,
+            params: Path<CommentId>,
+            data: Data<AppState<'_>>,
+        ) -> actix_web::Result<impl Responder> {
+            let Some(jwt) = get_authentication_data(&request, &data).await else {
+                return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+            };
+
+            comment_like::Entity::delete_many()
+                .filter(comment_like::Column::CommentId.eq(params.id))
+                .filter(comment_like::Column::UserId.eq(jwt.sub))
+                .exec(&data.db_connection)
+                .await
+                .map_err(|_| ErrorInternalServerError("Unable to remove like"))?;
+
+            let comment = find_comment(params.id, &data.db_connection).await?;
+
+            Ok(HttpResponse::Ok().json(json!({
+                "likes": comment.likes,
+                "liked": false,
+            })))
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use actix_web::{
+    error::ErrorInternalServerError, get, web::Data, HttpRequest, HttpResponse, Responder,
+};
+use actix_web_validator5::Query;
+use sea_orm::{sea_query::Query as SeaQuery, ColumnTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    entity::{subscription, video},
+    util::{channel::get_channels_info, get_authentication_data, video::find_public_videos},
+    AppState,
+};
+
+const FEED_PAGE_SIZE: u64 = 24;
+
+pub mod subscriptions {
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct GetSubscriptions {
+        #[validate(range(max = 1000))]
+        page: Option<u64>,
+    }
+
+    #[get("/feed/subscriptions")]
+    async fn get(
+        request: HttpRequest,
+        query: Query<GetSubscriptions>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::TemporaryRedirect()
+                .insert_header(("Cache-Control", "no-store"))
+                .insert_header(("Location", "/"))
+                .finish());
+        };
+
+        let page = query.page.unwrap_or_default();
+        let mut videos = find_public_videos()
+            .filter(
+                video::Column::UserId.in_subquery(
+                    SeaQuery::select()
+                        .column(subscription::Column::ChannelId)
+                        .from(subscription::Entity)
+                        .and_where(subscription::Column::UserId.eq(&jwt.sub))
+                        .to_owned(),
+                ),
+            )
+            .order_by_desc(video::Column::Timestamp)
+            .offset(page * FEED_PAGE_SIZE)
+            .limit(FEED_PAGE_SIZE + 1)
+            .all(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to find a videos"))?;
+        let has_next_page = videos.len() as u64 > FEED_PAGE_SIZE;
+
+        videos.truncate(FEED_PAGE_SIZE as usize);
+
+        let channels_info = get_channels_info(
+            &videos.iter().map(|video| video.user_id.clone()).collect(),
+            &data.clerk,
+            &data.redis_client,
+        )
+        .await;
+        let videos: Vec<Value> = videos
+            .iter()
+            .filter_map(|video| {
+                Some(json!({
+                    "uuid": video.uuid,
+                    "title": video.title,
+                    "views": video.views,
+                    "timestamp": video.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                    "duration": video.duration,
+                    "channel_info": channels_info.get(&video.user_id)?,
+                }))
+            })
+            .collect();
+
+        Ok(HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .insert_header(("Content-type", "text/html; charset=utf-8"))
+            .body(
+                data.handlebars
+                    .render(
+                        "feed",
+                        &json!({
+                            "title": "Abonnements",
+                            "videos": videos,
+                            "has_previous_page": page > 0,
+                            "has_next_page": has_next_page,
+                            "previous_page": page.saturating_sub(1),
+                            "next_page": page + 1,
+                        }),
+                    )
+                    .unwrap(),
+            ))
+    }
+}
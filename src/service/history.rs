@@ -0,0 +1,241 @@
+use ::uuid::Uuid;
+use actix_web::{
+    delete, error::ErrorInternalServerError, get, post, put, web::Data, HttpRequest, HttpResponse,
+    Responder,
+};
+use actix_web_validator5::{Json, Path, Query};
+use chrono::Utc;
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    QueryTrait, Set,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    entity::{video, watch_history, watch_history_pause},
+    util::{
+        channel::get_channels_info,
+        get_authentication_data,
+        history::{is_history_paused, record_watch},
+        video::{find_public_videos, find_video},
+    },
+    AppState,
+};
+
+const HISTORY_PAGE_SIZE: u64 = 24;
+
+#[derive(Deserialize, Validate, Debug)]
+struct GetHistory {
+    #[validate(range(max = 1000))]
+    page: Option<u64>,
+}
+
+#[get("/history")]
+async fn get(
+    request: HttpRequest,
+    query: Query<GetHistory>,
+    data: Data<AppState<'_>>,
+) -> actix_web::Result<impl Responder> {
+    let Some(jwt) = get_authentication_data(&request, &data).await else {
+        return Ok(HttpResponse::TemporaryRedirect()
+            .insert_header(("Cache-Control", "no-store"))
+            .insert_header(("Location", "/"))
+            .finish());
+    };
+
+    let page = query.page.unwrap_or_default();
+    let mut history = watch_history::Entity::find()
+        .filter(watch_history::Column::UserId.eq(&jwt.sub))
+        .filter(
+            watch_history::Column::Uuid.in_subquery(
+                find_public_videos()
+                    .select_only()
+                    .column(video::Column::Uuid)
+                    .into_query(),
+            ),
+        )
+        .order_by_desc(watch_history::Column::Timestamp)
+        .offset(page * HISTORY_PAGE_SIZE)
+        .limit(HISTORY_PAGE_SIZE + 1)
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find the watch history"))?;
+    let has_next_page = history.len() as u64 > HISTORY_PAGE_SIZE;
+
+    history.truncate(HISTORY_PAGE_SIZE as usize);
+
+    let videos = find_public_videos()
+        .filter(video::Column::Uuid.is_in(history.iter().map(|history| history.uuid)))
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find the watch history"))?;
+    let channels_info = get_channels_info(
+        &videos.iter().map(|video| video.user_id.clone()).collect(),
+        &data.clerk,
+        &data.redis_client,
+    )
+    .await;
+    let videos: Vec<Value> = history
+        .iter()
+        .filter_map(|history| {
+            let video = videos.iter().find(|video| video.uuid == history.uuid)?;
+            let channel_info = channels_info.get(&video.user_id)?;
+
+            Some(json!({
+                "uuid": video.uuid,
+                "title": video.title,
+                "views": video.views,
+                "timestamp": video.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                "duration": video.duration,
+                "channel_info": channel_info,
+                "position": history.position,
+                "completed": history.completed,
+                "watched_timestamp": history.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+            }))
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .insert_header(("Content-type", "text/html; charset=utf-8"))
+        .body(
+            data.handlebars
+                .render(
+                    "history",
+                    &json!({
+                        "videos": videos,
+                        "paused": is_history_paused(&jwt.sub, &data.db_connection).await,
+                        "has_previous_page": page > 0,
+                        "has_next_page": has_next_page,
+                        "previous_page": page.saturating_sub(1),
+                        "next_page": page + 1,
+                    }),
+                )
+                .unwrap(),
+        ))
+}
+
+#[delete("/history")]
+async fn delete(
+    request: HttpRequest,
+    data: Data<AppState<'_>>,
+) -> actix_web::Result<impl Responder> {
+    let Some(jwt) = get_authentication_data(&request, &data).await else {
+        return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+    };
+
+    watch_history::Entity::delete_many()
+        .filter(watch_history::Column::UserId.eq(&jwt.sub))
+        .exec(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to clear the watch history"))?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub mod pause {
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct PutPause {
+        paused: bool,
+    }
+
+    #[put("/history/pause")]
+    async fn put(
+        request: HttpRequest,
+        payload: Json<PutPause>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        if payload.paused {
+            watch_history_pause::Entity::insert(watch_history_pause::ActiveModel {
+                user_id: Set(jwt.sub),
+                timestamp: Set(Utc::now().naive_utc()),
+            })
+            .on_conflict(
+                OnConflict::column(watch_history_pause::Column::UserId)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(&data.db_connection)
+            .await
+        } else {
+            watch_history_pause::Entity::delete_by_id(jwt.sub)
+                .exec(&data.db_connection)
+                .await
+                .map(|result| result.rows_affected)
+        }
+        .map_err(|_| ErrorInternalServerError("Unable to update the watch history settings"))?;
+
+        Ok(HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .json(json!({ "paused": payload.paused })))
+    }
+}
+
+pub mod uuid {
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct History {
+        uuid: Uuid,
+    }
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct PostHistory {
+        #[validate(range(min = 0.0))]
+        position: f64,
+    }
+
+    /// Progress beacon sent by the player, segments are fetched ahead of what was actually watched.
+    #[post("/history/{uuid}")]
+    async fn post(
+        request: HttpRequest,
+        params: Path<History>,
+        payload: Json<PostHistory>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        let video = find_video(&params.uuid, &data.db_connection).await?;
+
+        record_watch(
+            &jwt.sub,
+            &video.uuid,
+            payload.position,
+            video.duration,
+            &data.db_connection,
+        )
+        .await?;
+
+        Ok(HttpResponse::Ok().finish())
+    }
+
+    #[delete("/history/{uuid}")]
+    async fn delete(
+        request: HttpRequest,
+        params: Path<History>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        watch_history::Entity::delete_by_id((jwt.sub, params.uuid))
+            .exec(&data.db_connection)
+            .await
+            .map_err(|_| {
+                ErrorInternalServerError("Unable to remove the video from the watch history")
+            })?;
+
+        Ok(HttpResponse::Ok().finish())
+    }
+}
@@ -1,5 +1,4 @@
 use std::{
-    collections::{HashMap, HashSet},
     str::FromStr,
     time::{Duration, SystemTime},
 };
@@ -8,95 +7,142 @@ use actix_web::{
     error::ErrorInternalServerError, get, web::Data, HttpRequest, HttpResponse, Responder,
 };
 use chrono::{DateTime, Utc};
-use futures::future::{join, join_all};
 use gorse_rs::{Feedback, User};
-use sea_orm::{ColumnTrait, Condition, EntityTrait, QueryFilter};
+use sea_orm::{ColumnTrait, QueryFilter, QuerySelect};
 use serde_json::{json, Value};
 use uuid::Uuid;
 
 use crate::{
     entity::video,
-    util::{channel::get_channel_info, get_authentication_data, get_gorse_user_id},
+    util::{
+        channel::get_channels_info,
+        get_authentication_data, get_gorse_user_id,
+        labels::get_user_labels,
+        video::{find_latest_videos, find_public_videos, find_trending_videos},
+    },
     AppState,
 };
 
-#[get("/")]
-async fn get(request: HttpRequest, data: Data<AppState<'_>>) -> actix_web::Result<impl Responder> {
-    let jwt = get_authentication_data(&request, &data.clerk).await;
-    let user_id = get_gorse_user_id(&request, &jwt).await;
-
-    if data.gorse_client.get_user(&user_id).await.is_err() {
-        data.gorse_client
-            .insert_user(&User {
-                user_id: user_id.clone(),
-                labels: Vec::new(),
-            })
-            .await
-            .map_err(|_| ErrorInternalServerError("Unable to add the new user"))?;
+const HOME_VIDEOS_LIMIT: u64 = 24;
+const HOME_TRENDING_DAYS: i64 = 7;
+
+/// Personalized items from Gorse, skipped while the breaker is open.
+async fn get_recommendation(user_id: Option<&str>, data: &AppState<'_>) -> Vec<String> {
+    let Some(user_id) = user_id else {
+        return Vec::new();
+    };
+    // an unknown user is an answer, only an unreachable Gorse counts against the breaker
+    let known = data
+        .gorse_breaker
+        .call(async { Ok::<_, ()>(data.gorse_client.get_user(user_id).await.is_ok()) })
+        .await;
+
+    match known {
+        None => return Vec::new(),
+        Some(false) => {
+            let labels = get_user_labels(user_id, &data.db_connection)
+                .await
+                .unwrap_or_default();
+            let inserted = data
+                .gorse_breaker
+                .call(data.gorse_client.insert_user(&User {
+                    user_id: user_id.to_string(),
+                    labels,
+                }))
+                .await;
+
+            if inserted.is_none() {
+                return Vec::new();
+            }
+        }
+        Some(true) => {}
     }
 
-    let recommendation = data
-        .gorse_client
-        .get_recommend(&user_id)
+    data.gorse_breaker
+        .call(data.gorse_client.get_recommend(user_id))
         .await
-        .unwrap_or_default();
+        .unwrap_or_default()
+}
+
+#[get("/")]
+async fn get(request: HttpRequest, data: Data<AppState<'_>>) -> actix_web::Result<impl Responder> {
+    let jwt = get_authentication_data(&request, &data).await;
+    let user_id = get_gorse_user_id(&request, &jwt, &data).await;
+    let recommendation = get_recommendation(user_id.as_deref(), &data).await;
     let recommendation_timestamp =
         (DateTime::<Utc>::from(SystemTime::now()) + Duration::new(3600, 0)).to_rfc3339();
-    let conditions = recommendation
-        .iter()
-        .fold(Condition::any(), |condition, item_id| {
-            condition.add(video::Column::Uuid.eq(Uuid::from_str(&item_id).unwrap()))
-        });
-
-    let (_, video_model) = join(
-        data.gorse_client.insert_feedback(
-            &recommendation
-                .iter()
-                .map(|item_id| Feedback {
-                    feedback_type: "display".to_string(),
-                    user_id: user_id.clone(),
-                    item_id: item_id.clone(),
-                    timestamp: recommendation_timestamp.clone(),
-                })
-                .collect::<Vec<Feedback>>(),
-        ),
-        video::Entity::find()
-            .filter(conditions)
-            .all(&data.db_connection),
-    )
-    .await;
-
-    let videos_model =
-        video_model.map_err(|_| ErrorInternalServerError("Unable to find a videos"))?;
-    let user_ids: HashSet<String> = videos_model
+    let uuids: Vec<Uuid> = recommendation
         .iter()
-        .map(|video| video.user_id.clone())
+        .filter_map(|item_id| Uuid::from_str(item_id).ok())
         .collect();
-    let mut channels_info = HashMap::new();
 
-    for channel_info in join_all(
-        user_ids
-            .iter()
-            .map(|user_id| get_channel_info(&user_id, &data.clerk, &data.redis_client)),
-    )
-    .await
-    {
-        let channel_info = channel_info?;
+    if let Some(user_id) = user_id.filter(|_| !recommendation.is_empty()) {
+        data.gorse_breaker
+            .call(
+                data.gorse_client.insert_feedback(
+                    &recommendation
+                        .iter()
+                        .map(|item_id| Feedback {
+                            feedback_type: "display".to_string(),
+                            user_id: user_id.clone(),
+                            item_id: item_id.clone(),
+                            timestamp: recommendation_timestamp.clone(),
+                        })
+                        .collect::<Vec<Feedback>>(),
+                ),
+            )
+            .await;
+    }
+
+    let mut videos_model = find_public_videos()
+        .filter(video::Column::Uuid.is_in(uuids.clone()))
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find a videos"))?;
 
-        channels_info.insert(channel_info.user_id.clone(), channel_info);
+    videos_model.sort_by_key(|video| uuids.iter().position(|uuid| uuid == &video.uuid));
+
+    // new instances and new users get the trending videos, then the latest ones
+    for fallback in [
+        find_trending_videos(HOME_TRENDING_DAYS),
+        find_latest_videos(),
+    ] {
+        let limit = HOME_VIDEOS_LIMIT.saturating_sub(videos_model.len() as u64);
+
+        if limit == 0 {
+            break;
+        }
+
+        videos_model.extend(
+            fallback
+                .filter(video::Column::Uuid.is_not_in(videos_model.iter().map(|video| video.uuid)))
+                .limit(limit)
+                .all(&data.db_connection)
+                .await
+                .map_err(|_| ErrorInternalServerError("Unable to find a videos"))?,
+        );
     }
 
+    let channels_info = get_channels_info(
+        &videos_model
+            .iter()
+            .map(|video| video.user_id.clone())
+            .collect(),
+        &data.clerk,
+        &data.redis_client,
+    )
+    .await;
     let videos: Vec<Value> = videos_model
         .iter()
-        .map(|video| {
-            json!({
+        .filter_map(|video| {
+            Some(json!({
                 "uuid": video.uuid,
                 "title": video.title,
                 "views": video.views,
                 "timestamp": video.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
                 "duration": video.duration,
-                "channel_info": channels_info[&video.user_id],
-            })
+                "channel_info": channels_info.get(&video.user_id)?,
+            }))
         })
         .collect();
 
@@ -6,13 +6,54 @@ use actix_web::{
 };
 use actix_web_validator5::Path;
 use chrono::{DateTime, Utc};
-use futures::{future::join, FutureExt};
+use futures::future::join;
 use gorse_rs::Feedback;
-use sea_orm::{ActiveModelTrait, Set};
+use sea_orm::{sea_query::OnConflict, ColumnTrait, EntityTrait, QueryFilter, Set};
 use serde::Deserialize;
+use serde_json::json;
 use validator::Validate;
 
-use crate::{entity::like, util::get_authentication_data, AppState};
+use crate::{
+    entity::{like, sea_orm_active_enums::VideoUploadState},
+    util::{
+        get_authentication_data,
+        notification::notify_like_milestone,
+        trending::{record_engagement, Engagement},
+        video::{find_video, find_visible_video, get_resolutions},
+    },
+    AppState, MeilliDocument,
+};
+
+async fn like_state(
+    uuid: &Uuid,
+    liked: bool,
+    data: &Data<AppState<'_>>,
+) -> actix_web::Result<HttpResponse> {
+    let video = find_visible_video(uuid, &data.db_connection).await?;
+
+    // an update would create a partial document for a video that isn't indexed
+    if !get_resolutions(&video, VideoUploadState::eq, VideoUploadState::Available).is_empty() {
+        data.video_index
+            .add_or_update(
+                &[MeilliDocument {
+                    id: uuid.to_string(),
+                    value: json!({
+                        "likes": video.likes
+                    }),
+                }],
+                Some("id"),
+            )
+            .await
+            .ok();
+    }
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(json!({
+            "likes": video.likes,
+            "liked": liked,
+        })))
+}
 
 pub mod uuid {
     use super::*;
@@ -28,29 +69,49 @@ pub mod uuid {
         params: Path<PostLike>,
         data: Data<AppState<'_>>,
     ) -> actix_web::Result<impl Responder> {
-        if let Some(jwt) = get_authentication_data(&request, &data.clerk).await {
-            let like = like::ActiveModel {
-                uuid: Set(params.uuid),
-                user_id: Set(jwt.sub.clone()),
-            };
-
-            let (_, db) = join(
-                data.gorse_client
-                    .insert_feedback(&vec![Feedback {
-                        feedback_type: "like".to_string(),
-                        user_id: jwt.sub,
-                        item_id: params.uuid.to_string(),
-                        timestamp: DateTime::<Utc>::from(SystemTime::now()).to_rfc3339(),
-                    }])
-                    .boxed(),
-                like.insert(&data.db_connection),
-            )
-            .await;
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        let previous_likes = find_visible_video(&params.uuid, &data.db_connection)
+            .await?
+            .likes;
 
-            db.map_err(|_| ErrorInternalServerError("Unable to add like"))?;
+        let like = like::ActiveModel {
+            uuid: Set(params.uuid),
+            user_id: Set(jwt.sub.clone()),
+            ..Default::default()
+        };
+
+        let (_, db) = join(
+            data.gorse_client.insert_feedback(&vec![Feedback {
+                feedback_type: "like".to_string(),
+                user_id: jwt.sub,
+                item_id: params.uuid.to_string(),
+                timestamp: DateTime::<Utc>::from(SystemTime::now()).to_rfc3339(),
+            }]),
+            like::Entity::insert(like)
+                .on_conflict(
+                    OnConflict::columns([like::Column::Uuid, like::Column::UserId])
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .exec_without_returning(&data.db_connection),
+        )
+        .await;
+
+        // only a new like can reach a milestone
+        if db.map_err(|_| ErrorInternalServerError("Unable to add like"))? > 0 {
+            record_engagement(&params.uuid, Engagement::Like, &data.redis_client).await;
+
+            let video = find_video(&params.uuid, &data.db_connection).await?;
+
+            notify_like_milestone(&video, previous_likes, &data)
+                .await
+                .ok();
         }
 
-        Ok(HttpResponse::Ok())
+        like_state(&params.uuid, true, &data).await
     }
 
     #[derive(Deserialize, Validate, Debug)]
@@ -64,17 +125,31 @@ pub mod uuid {
         params: Path<DeleteLike>,
         data: Data<AppState<'_>>,
     ) -> actix_web::Result<impl Responder> {
-        if let Some(jwt) = get_authentication_data(&request, &data.clerk).await {
-            let like = like::ActiveModel {
-                uuid: Set(params.uuid),
-                user_id: Set(jwt.sub),
-            };
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
 
-            like.delete(&data.db_connection)
-                .await
-                .map_err(|_| ErrorInternalServerError("Unable to remove like"))?;
+        find_visible_video(&params.uuid, &data.db_connection).await?;
+
+        let (_, db) = join(
+            data.gorse_api
+                .delete_feedback("like", &jwt.sub, &params.uuid.to_string()),
+            like::Entity::delete_many()
+                .filter(like::Column::Uuid.eq(params.uuid))
+                .filter(like::Column::UserId.eq(jwt.sub.clone()))
+                .exec(&data.db_connection),
+        )
+        .await;
+
+        // only a removed like takes its engagement back
+        if db
+            .map_err(|_| ErrorInternalServerError("Unable to remove like"))?
+            .rows_affected
+            > 0
+        {
+            record_engagement(&params.uuid, Engagement::Unlike, &data.redis_client).await;
         }
 
-        Ok(HttpResponse::Ok())
+        like_state(&params.uuid, false, &data).await
     }
 }
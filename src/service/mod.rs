@@ -1,9 +1,21 @@
+pub mod admin;
+pub mod channel;
+pub mod comment;
+pub mod feed;
+pub mod history;
 pub mod index;
 pub mod like;
+pub mod moderation;
+pub mod notification;
+pub mod playlist;
+pub mod report;
 pub mod results;
+pub mod session;
 pub mod share;
+pub mod subscription;
 pub mod thumbnail;
 pub mod together;
+pub mod trending;
 pub mod upload;
 pub mod video;
 pub mod watch;
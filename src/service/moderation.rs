@@ -0,0 +1,269 @@
+use std::collections::{HashMap, HashSet};
+
+use ::uuid::Uuid;
+use actix_web::{
+    delete, error::ErrorInternalServerError, get, patch, put, web::Data, HttpRequest, HttpResponse,
+    Responder,
+};
+use actix_web_validator5::{Json, Path};
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    entity::{comment, comment_block, comment_moderation, video},
+    util::{
+        channel::{channel_info_or_unknown, get_channel_info, get_channels_info},
+        comment::split_banned_words,
+        get_authentication_data,
+        video::find_video,
+    },
+    AppState,
+};
+
+#[get("/moderation")]
+async fn get(request: HttpRequest, data: Data<AppState<'_>>) -> actix_web::Result<impl Responder> {
+    let Some(jwt) = get_authentication_data(&request, &data).await else {
+        return Ok(HttpResponse::TemporaryRedirect()
+            .insert_header(("Cache-Control", "no-store"))
+            .insert_header(("Location", "/"))
+            .finish());
+    };
+
+    let videos = video::Entity::find()
+        .filter(video::Column::UserId.eq(&jwt.sub))
+        .order_by_desc(video::Column::Timestamp)
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find a videos"))?;
+    let banned_words = comment_moderation::Entity::find_by_id(jwt.sub.clone())
+        .one(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find moderation settings"))?
+        .map(|moderation| split_banned_words(&moderation.banned_words))
+        .unwrap_or_default();
+    let blocked_user_ids: HashSet<String> = comment_block::Entity::find()
+        .filter(comment_block::Column::UserId.eq(&jwt.sub))
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find blocked users"))?
+        .into_iter()
+        .map(|comment_block| comment_block.blocked_user_id)
+        .collect();
+    let held_comments = comment::Entity::find()
+        .filter(comment::Column::Uuid.is_in(videos.iter().map(|video| video.uuid)))
+        .filter(comment::Column::Held.eq(true))
+        .filter(comment::Column::Deleted.eq(false))
+        .filter(comment::Column::Hidden.eq(false))
+        .order_by_desc(comment::Column::Timestamp)
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find held comments"))?;
+    let mut user_ids = blocked_user_ids.clone();
+
+    user_ids.extend(held_comments.iter().map(|comment| comment.user_id.clone()));
+
+    let channels_info = get_channels_info(&user_ids, &data.clerk, &data.redis_client).await;
+    let video_titles: HashMap<Uuid, &String> = videos
+        .iter()
+        .map(|video| (video.uuid, &video.title))
+        .collect();
+    let held_comments: Vec<Value> = held_comments
+        .iter()
+        .map(|comment| {
+            json!({
+                "id": comment.id,
+                "uuid": comment.uuid,
+                "title": video_titles[&comment.uuid],
+                "body": comment.body,
+                "timestamp": comment.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                "channel_info": channel_info_or_unknown(&channels_info, &comment.user_id),
+            })
+        })
+        .collect();
+    let blocked_users: Vec<Value> = blocked_user_ids
+        .iter()
+        // an account deleted since it was blocked has nothing to show
+        .filter_map(|user_id| channels_info.get(user_id))
+        .map(|channel_info| serde_json::to_value(channel_info).unwrap())
+        .collect();
+    let videos: Vec<Value> = videos
+        .iter()
+        .map(|video| {
+            json!({
+                "uuid": video.uuid,
+                "title": video.title,
+                "comments_disabled": video.comments_disabled,
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .insert_header(("Content-type", "text/html; charset=utf-8"))
+        .body(
+            data.handlebars
+                .render(
+                    "moderation",
+                    &json!({
+                        "banned_words": banned_words.join(", "),
+                        "blocked_users": blocked_users,
+                        "held_comments": held_comments,
+                        "videos": videos,
+                    }),
+                )
+                .unwrap(),
+        ))
+}
+
+pub mod banned_words {
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct PutBannedWords {
+        #[validate(length(min = 0, max = 5000))]
+        banned_words: String,
+    }
+
+    #[put("/moderation/banned-words")]
+    async fn put(
+        request: HttpRequest,
+        payload: Json<PutBannedWords>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        let banned_words = split_banned_words(&payload.banned_words).join(",");
+
+        comment_moderation::Entity::insert(comment_moderation::ActiveModel {
+            user_id: Set(jwt.sub),
+            banned_words: Set(banned_words),
+        })
+        .on_conflict(
+            OnConflict::column(comment_moderation::Column::UserId)
+                .update_column(comment_moderation::Column::BannedWords)
+                .to_owned(),
+        )
+        .exec_without_returning(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to save banned words"))?;
+
+        Ok(HttpResponse::Ok().finish())
+    }
+}
+
+pub mod block {
+    use super::*;
+
+    pub mod user_id {
+        use super::*;
+
+        #[derive(Deserialize, Validate, Debug)]
+        struct BlockUser {
+            #[validate(length(min = 1, max = 32))]
+            user_id: String,
+        }
+
+        #[put("/moderation/block/{user_id}")]
+        async fn put(
+            request: HttpRequest,
+            params: Path<BlockUser>,
+            data: Data<AppState<'_>>,
+        ) -> actix_web::Result<impl Responder> {
+            let Some(jwt) = get_authentication_data(&request, &data).await else {
+                return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+            };
+
+            if params.user_id == jwt.sub {
+                return Ok(HttpResponse::BadRequest().body("You cannot block yourself"));
+            }
+
+            get_channel_info(&params.user_id, &data.clerk, &data.redis_client).await?;
+
+            comment_block::Entity::insert(comment_block::ActiveModel {
+                user_id: Set(jwt.sub),
+                blocked_user_id: Set(params.user_id.clone()),
+            })
+            .on_conflict(
+                OnConflict::columns([
+                    comment_block::Column::UserId,
+                    comment_block::Column::BlockedUserId,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to block user"))?;
+
+            Ok(HttpResponse::Ok().finish())
+        }
+
+        #[delete("/moderation/block/{user_id}")]
+        async fn delete(
+            request: HttpRequest,
+            params: Path<BlockUser>,
+            data: Data<AppState<'_>>,
+        ) -> actix_web::Result<impl Responder> {
+            let Some(jwt) = get_authentication_data(&request, &data).await else {
+                return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+            };
+
+            comment_block::Entity::delete_many()
+                .filter(comment_block::Column::UserId.eq(jwt.sub))
+                .filter(comment_block::Column::BlockedUserId.eq(&params.user_id))
+                .exec(&data.db_connection)
+                .await
+                .map_err(|_| ErrorInternalServerError("Unable to unblock user"))?;
+
+            Ok(HttpResponse::Ok().finish())
+        }
+    }
+}
+
+pub mod uuid {
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct PatchModeration {
+        uuid: Uuid,
+    }
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct PatchModerationPayload {
+        comments_disabled: bool,
+    }
+
+    #[patch("/moderation/{uuid}")]
+    async fn patch(
+        request: HttpRequest,
+        params: Path<PatchModeration>,
+        payload: Json<PatchModerationPayload>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        let video = find_video(&params.uuid, &data.db_connection).await?;
+
+        if video.user_id != jwt.sub {
+            return Ok(HttpResponse::Forbidden().body("You cannot moderate another user's video"));
+        }
+
+        let mut video = video::ActiveModel::from(video);
+
+        video.comments_disabled = Set(payload.comments_disabled);
+        video
+            .update(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to update the video"))?;
+
+        Ok(HttpResponse::Ok().finish())
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::{HashMap, HashSet};
+
+use ::uuid::Uuid;
+use actix_web::{
+    error::ErrorInternalServerError, get, post, web::Data, HttpRequest, HttpResponse, Responder,
+};
+use actix_web_validator5::{Json, Query};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    entity::{comment, notification, video},
+    util::{
+        channel::get_channels_info,
+        get_authentication_data,
+        notification::{find_notifications, NOTIFICATION_PAGE_SIZE},
+    },
+    AppState,
+};
+
+async fn count_unread(user_id: &str, db_connection: &DatabaseConnection) -> actix_web::Result<u64> {
+    find_notifications(user_id)
+        .filter(notification::Column::Read.eq(false))
+        .count(db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to count notifications"))
+}
+
+#[derive(Deserialize, Validate, Debug)]
+struct GetNotification {
+    #[validate(range(max = 1000))]
+    page: Option<u64>,
+}
+
+#[get("/notification")]
+async fn get(
+    request: HttpRequest,
+    query: Query<GetNotification>,
+    data: Data<AppState<'_>>,
+) -> actix_web::Result<impl Responder> {
+    let Some(jwt) = get_authentication_data(&request, &data).await else {
+        return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+    };
+
+    let page = query.page.unwrap_or_default();
+    let mut notifications = find_notifications(&jwt.sub)
+        .order_by_desc(notification::Column::Timestamp)
+        .order_by_desc(notification::Column::Id)
+        .offset(page * NOTIFICATION_PAGE_SIZE)
+        .limit(NOTIFICATION_PAGE_SIZE + 1)
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find notifications"))?;
+    let has_more = notifications.len() as u64 > NOTIFICATION_PAGE_SIZE;
+
+    notifications.truncate(NOTIFICATION_PAGE_SIZE as usize);
+
+    let titles: HashMap<Uuid, String> = video::Entity::find()
+        .select_only()
+        .column(video::Column::Uuid)
+        .column(video::Column::Title)
+        .filter(
+            video::Column::Uuid.is_in(
+                notifications
+                    .iter()
+                    .map(|notification| notification.uuid)
+                    .collect::<HashSet<Uuid>>(),
+            ),
+        )
+        .into_tuple::<(Uuid, String)>()
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find a videos"))?
+        .into_iter()
+        .collect();
+    // deleted comments are kept out, the notification then links to the video only
+    let comments: HashMap<i64, String> = comment::Entity::find()
+        .filter(
+            comment::Column::Id.is_in(
+                notifications
+                    .iter()
+                    .filter_map(|notification| notification.comment_id),
+            ),
+        )
+        .filter(comment::Column::Deleted.eq(false))
+        .filter(comment::Column::Hidden.eq(false))
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find comments"))?
+        .into_iter()
+        .map(|comment| (comment.id, comment.body))
+        .collect();
+    let channels_info = get_channels_info(
+        &notifications
+            .iter()
+            .filter_map(|notification| notification.actor_id.clone())
+            .collect(),
+        &data.clerk,
+        &data.redis_client,
+    )
+    .await;
+    let notifications: Vec<Value> = notifications
+        .iter()
+        .map(|notification| {
+            json!({
+                "id": notification.id,
+                "type": notification.notification_type,
+                "uuid": notification.uuid,
+                "title": titles.get(&notification.uuid),
+                "comment": notification.comment_id.and_then(|comment_id| {
+                    comments.get(&comment_id).map(|body| {
+                        json!({
+                            "id": comment_id,
+                            "body": body,
+                        })
+                    })
+                }),
+                "value": notification.value,
+                "timestamp": notification.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                "read": notification.read,
+                "channel_info": notification
+                    .actor_id
+                    .as_ref()
+                    .and_then(|actor_id| channels_info.get(actor_id)),
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(json!({
+            "notifications": notifications,
+            "page": page,
+            "has_more": has_more,
+            "unread": count_unread(&jwt.sub, &data.db_connection).await?,
+        })))
+}
+
+pub mod unread {
+    use super::*;
+
+    #[get("/notification/unread")]
+    async fn get(
+        request: HttpRequest,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        Ok(HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .json(json!({
+                "unread": count_unread(&jwt.sub, &data.db_connection).await?,
+            })))
+    }
+}
+
+pub mod read {
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct PostRead {
+        /// Every notification is marked as read when no ids are given.
+        #[validate(length(min = 1, max = 100))]
+        ids: Option<Vec<i64>>,
+    }
+
+    #[post("/notification/read")]
+    async fn post(
+        request: HttpRequest,
+        payload: Json<PostRead>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        let mut update = notification::Entity::update_many()
+            .col_expr(notification::Column::Read, true.into())
+            .filter(notification::Column::UserId.eq(&jwt.sub))
+            .filter(notification::Column::Read.eq(false));
+
+        if let Some(ids) = &payload.ids {
+            update = update.filter(notification::Column::Id.is_in(ids.clone()));
+        }
+
+        update
+            .exec(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to mark notifications as read"))?;
+
+        Ok(HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .json(json!({
+                "unread": count_unread(&jwt.sub, &data.db_connection).await?,
+            })))
+    }
+}
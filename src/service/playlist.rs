@@ -0,0 +1,623 @@
+use std::collections::HashSet;
+
+use ::uuid::Uuid;
+use actix_web::{
+    delete,
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    get, patch, put,
+    web::Data,
+    HttpRequest, HttpResponse, Responder,
+};
+use actix_web_validator5::{Json, Path, Query};
+use chrono::Utc;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ActiveModelTrait, ColumnTrait, DatabaseTransaction, EntityTrait, ModelTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    entity::{playlist, playlist_item, sea_orm_active_enums::PlaylistVisibility, video},
+    util::{
+        channel::{channel_info_or_unknown, get_channel_info, get_channels_info},
+        get_authentication_data,
+        playlist::{
+            count_playlist_items, find_playlist, find_visible_playlist, find_watch_later,
+            get_liked_videos, get_playlist_videos, update_playlist_index, LIKED_LIST, LIKED_TITLE,
+            PLAYLIST_MAX_SIZE,
+        },
+        video::find_visible_video,
+    },
+    AppState,
+};
+
+fn trim_description(description: &Option<String>) -> Option<String> {
+    description
+        .as_deref()
+        .map(str::trim)
+        .filter(|description| !description.is_empty())
+        .map(str::to_string)
+}
+
+/// Marks the playlist as updated and keeps the search base in sync.
+async fn touch_playlist(playlist: playlist::Model, data: &AppState<'_>) -> actix_web::Result<()> {
+    let mut playlist = playlist::ActiveModel::from(playlist);
+
+    playlist.update_timestamp = Set(Utc::now().naive_utc());
+
+    let playlist = playlist
+        .update(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to update the playlist"))?;
+
+    update_playlist_index(&playlist, data).await
+}
+
+/// Appends the video to the playlist, whose row is locked so concurrent adds get distinct positions.
+async fn add_video(
+    playlist_id: &Uuid,
+    uuid: &Uuid,
+    transaction: &DatabaseTransaction,
+) -> actix_web::Result<()> {
+    playlist::Entity::find_by_id(*playlist_id)
+        .lock_exclusive()
+        .one(transaction)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to add the video to the playlist"))?;
+
+    if count_playlist_items(playlist_id, transaction).await? >= PLAYLIST_MAX_SIZE {
+        return Err(ErrorBadRequest("The playlist is full"));
+    }
+
+    let position = playlist_item::Entity::find()
+        .select_only()
+        .column_as(playlist_item::Column::Position.max(), "position")
+        .filter(playlist_item::Column::PlaylistId.eq(*playlist_id))
+        .into_tuple::<Option<i32>>()
+        .one(transaction)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to add the video to the playlist"))?
+        .flatten()
+        .map_or(0, |position| position + 1);
+
+    playlist_item::Entity::insert(playlist_item::ActiveModel {
+        playlist_id: Set(*playlist_id),
+        uuid: Set(*uuid),
+        position: Set(position),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([
+            playlist_item::Column::PlaylistId,
+            playlist_item::Column::Uuid,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
+    .exec_without_returning(transaction)
+    .await
+    .map_err(|_| ErrorInternalServerError("Unable to add the video to the playlist"))?;
+
+    Ok(())
+}
+
+/// Renders the playlist page, the liked videos list has no row so its fields come from the caller.
+async fn render_playlist(
+    list: &str,
+    channel_id: &str,
+    videos: Vec<video::Model>,
+    mut context: Value,
+    data: &AppState<'_>,
+) -> actix_web::Result<HttpResponse> {
+    let channel_info = get_channel_info(channel_id, &data.clerk, &data.redis_client).await?;
+    let channels_info = get_channels_info(
+        &videos.iter().map(|video| video.user_id.clone()).collect(),
+        &data.clerk,
+        &data.redis_client,
+    )
+    .await;
+    let videos: Vec<Value> = videos
+        .iter()
+        .map(|video| {
+            json!({
+                "uuid": video.uuid,
+                "title": video.title,
+                "views": video.views,
+                "timestamp": video.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                "duration": video.duration,
+                "channel_info": channel_info_or_unknown(&channels_info, &video.user_id),
+                "list": list,
+            })
+        })
+        .collect();
+
+    context["id"] = json!(list);
+    context["channel_info"] = json!(channel_info);
+    context["video_count"] = json!(videos.len());
+    context["videos"] = json!(videos);
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .insert_header(("Content-type", "text/html; charset=utf-8"))
+        .body(data.handlebars.render("playlist", &context).unwrap()))
+}
+
+#[derive(Deserialize, Validate, Debug)]
+struct GetPlaylists {
+    uuid: Option<Uuid>,
+}
+
+#[get("/playlist")]
+async fn get(
+    request: HttpRequest,
+    query: Query<GetPlaylists>,
+    data: Data<AppState<'_>>,
+) -> actix_web::Result<impl Responder> {
+    let Some(jwt) = get_authentication_data(&request, &data).await else {
+        return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+    };
+
+    // so Watch Later is always offered first
+    find_watch_later(&jwt.sub, &data.db_connection).await?;
+
+    let playlists = playlist::Entity::find()
+        .filter(playlist::Column::UserId.eq(&jwt.sub))
+        .order_by_desc(playlist::Column::WatchLater)
+        .order_by_desc(playlist::Column::UpdateTimestamp)
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find playlists"))?;
+    let containing: HashSet<Uuid> = match query.uuid {
+        Some(uuid) => playlist_item::Entity::find()
+            .select_only()
+            .column(playlist_item::Column::PlaylistId)
+            .filter(playlist_item::Column::Uuid.eq(uuid))
+            .filter(
+                playlist_item::Column::PlaylistId
+                    .is_in(playlists.iter().map(|playlist| playlist.id)),
+            )
+            .into_tuple()
+            .all(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to find playlists"))?
+            .into_iter()
+            .collect(),
+        None => HashSet::new(),
+    };
+    let playlists: Vec<Value> = playlists
+        .iter()
+        .map(|playlist| {
+            json!({
+                "id": playlist.id,
+                "title": playlist.title,
+                "visibility": playlist.visibility,
+                "contains": containing.contains(&playlist.id),
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(playlists))
+}
+
+#[derive(Deserialize, Validate, Debug)]
+struct PutPlaylist {
+    #[validate(length(min = 1, max = 100))]
+    title: String,
+    #[validate(length(min = 0, max = 5000))]
+    description: Option<String>,
+    visibility: Option<PlaylistVisibility>,
+    uuid: Option<Uuid>,
+}
+
+#[put("/playlist")]
+async fn put(
+    request: HttpRequest,
+    payload: Json<PutPlaylist>,
+    data: Data<AppState<'_>>,
+) -> actix_web::Result<impl Responder> {
+    let Some(jwt) = get_authentication_data(&request, &data).await else {
+        return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+    };
+
+    let title = payload.title.trim();
+
+    if title.is_empty() {
+        return Err(ErrorBadRequest("The title is empty"));
+    }
+
+    // the playlist can be created from a video, which is then added in the same transaction
+    if let Some(uuid) = &payload.uuid {
+        find_visible_video(uuid, &data.db_connection).await?;
+    }
+
+    let transaction = data
+        .db_connection
+        .begin()
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to create the playlist"))?;
+    let playlist = playlist::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(jwt.sub),
+        title: Set(title.to_string()),
+        description: Set(trim_description(&payload.description)),
+        visibility: Set(payload.visibility.unwrap_or_default()),
+        ..Default::default()
+    }
+    .insert(&transaction)
+    .await
+    .map_err(|_| ErrorInternalServerError("Unable to create the playlist"))?;
+
+    if let Some(uuid) = &payload.uuid {
+        add_video(&playlist.id, uuid, &transaction).await?;
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to create the playlist"))?;
+
+    update_playlist_index(&playlist, &data).await?;
+
+    Ok(HttpResponse::Ok().body(playlist.id.to_string()))
+}
+
+pub mod watch_later {
+    use super::*;
+
+    #[get("/playlist/watch_later")]
+    async fn get(
+        request: HttpRequest,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::TemporaryRedirect()
+                .insert_header(("Cache-Control", "no-store"))
+                .insert_header(("Location", "/"))
+                .finish());
+        };
+
+        let playlist = find_watch_later(&jwt.sub, &data.db_connection).await?;
+
+        Ok(HttpResponse::TemporaryRedirect()
+            .insert_header(("Cache-Control", "no-store"))
+            .insert_header(("Location", format!("/playlist/{}", playlist.id)))
+            .finish())
+    }
+}
+
+pub mod liked {
+    use super::*;
+
+    #[get("/playlist/liked")]
+    async fn get(
+        request: HttpRequest,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::TemporaryRedirect()
+                .insert_header(("Cache-Control", "no-store"))
+                .insert_header(("Location", "/"))
+                .finish());
+        };
+
+        let videos = get_liked_videos(&jwt.sub, &data.db_connection).await?;
+
+        render_playlist(
+            LIKED_LIST,
+            &jwt.sub,
+            videos,
+            json!({
+                "title": LIKED_TITLE,
+                "visibility": PlaylistVisibility::Private,
+                "update_timestamp": Utc::now().format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                "owner": false,
+            }),
+            &data,
+        )
+        .await
+    }
+}
+
+pub mod id {
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct PlaylistId {
+        id: Uuid,
+    }
+
+    #[get("/playlist/{id}")]
+    async fn get(
+        request: HttpRequest,
+        params: Path<PlaylistId>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let jwt = get_authentication_data(&request, &data).await;
+        let playlist = find_visible_playlist(
+            &params.id,
+            jwt.as_ref().map(|jwt| jwt.sub.as_str()),
+            &data.db_connection,
+        )
+        .await?;
+        let videos = get_playlist_videos(&playlist.id, &data.db_connection).await?;
+
+        render_playlist(
+            &playlist.id.to_string(),
+            &playlist.user_id,
+            videos,
+            json!({
+                "title": playlist.title,
+                "description": playlist.description,
+                "visibility": playlist.visibility,
+                "update_timestamp": playlist.update_timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                "owner": jwt.is_some_and(|jwt| jwt.sub == playlist.user_id),
+                "watch_later": playlist.watch_later,
+            }),
+            &data,
+        )
+        .await
+    }
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct PatchPlaylist {
+        #[validate(length(min = 1, max = 100))]
+        title: Option<String>,
+        #[validate(length(min = 0, max = 5000))]
+        description: Option<String>,
+        visibility: Option<PlaylistVisibility>,
+    }
+
+    #[patch("/playlist/{id}")]
+    async fn patch(
+        request: HttpRequest,
+        params: Path<PlaylistId>,
+        payload: Json<PatchPlaylist>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        let playlist = find_playlist(&params.id, &data.db_connection).await?;
+
+        if playlist.user_id != jwt.sub {
+            return Ok(HttpResponse::Forbidden().body("You cannot edit another user's playlist"));
+        }
+
+        if playlist.watch_later {
+            return Err(ErrorBadRequest("The Watch Later playlist cannot be edited"));
+        }
+
+        let mut playlist = playlist::ActiveModel::from(playlist);
+
+        if let Some(title) = &payload.title {
+            let title = title.trim();
+
+            if title.is_empty() {
+                return Err(ErrorBadRequest("The title is empty"));
+            }
+
+            playlist.title = Set(title.to_string());
+        }
+
+        if payload.description.is_some() {
+            playlist.description = Set(trim_description(&payload.description));
+        }
+
+        if let Some(visibility) = payload.visibility {
+            playlist.visibility = Set(visibility);
+        }
+
+        playlist.update_timestamp = Set(Utc::now().naive_utc());
+
+        let playlist = playlist
+            .update(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to update the playlist"))?;
+
+        update_playlist_index(&playlist, &data).await?;
+
+        Ok(HttpResponse::Ok().finish())
+    }
+
+    #[delete("/playlist/{id}")]
+    async fn delete(
+        request: HttpRequest,
+        params: Path<PlaylistId>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        let playlist = find_playlist(&params.id, &data.db_connection).await?;
+
+        if playlist.user_id != jwt.sub {
+            return Ok(HttpResponse::Forbidden().body("You cannot delete another user's playlist"));
+        }
+
+        if playlist.watch_later {
+            return Err(ErrorBadRequest(
+                "The Watch Later playlist cannot be deleted",
+            ));
+        }
+
+        data.playlist_index
+            .delete_document(playlist.id.to_string())
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to update the search base"))?
+            .wait_for_completion(&data.meillisearch_client, None, None)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to update the search base"))?;
+        playlist
+            .delete(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to delete the playlist"))?;
+
+        Ok(HttpResponse::Ok().finish())
+    }
+
+    pub mod order {
+        use super::*;
+
+        #[derive(Deserialize, Validate, Debug)]
+        struct PutOrder {
+            #[validate(length(min = 1, max = 500))]
+            uuids: Vec<Uuid>,
+        }
+
+        #[put("/playlist/{id}/order")]
+        async fn put(
+            request: HttpRequest,
+            params: Path<PlaylistId>,
+            payload: Json<PutOrder>,
+            data: Data<AppState<'_>>,
+        ) -> actix_web::Result<impl Responder> {
+            let Some(jwt) = get_authentication_data(&request, &data).await else {
+                return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+            };
+
+            let playlist = find_playlist(&params.id, &data.db_connection).await?;
+
+            if playlist.user_id != jwt.sub {
+                return Ok(
+                    HttpResponse::Forbidden().body("You cannot edit another user's playlist")
+                );
+            }
+
+            let transaction = data
+                .db_connection
+                .begin()
+                .await
+                .map_err(|_| ErrorInternalServerError("Unable to reorder the playlist"))?;
+
+            playlist::Entity::find_by_id(playlist.id)
+                .lock_exclusive()
+                .one(&transaction)
+                .await
+                .map_err(|_| ErrorInternalServerError("Unable to reorder the playlist"))?;
+
+            let items: Vec<Uuid> = playlist_item::Entity::find()
+                .select_only()
+                .column(playlist_item::Column::Uuid)
+                .filter(playlist_item::Column::PlaylistId.eq(playlist.id))
+                .order_by_asc(playlist_item::Column::Position)
+                .into_tuple()
+                .all(&transaction)
+                .await
+                .map_err(|_| ErrorInternalServerError("Unable to reorder the playlist"))?;
+            let known: HashSet<Uuid> = items.iter().copied().collect();
+            let mut seen = HashSet::new();
+            // videos left out of the new order keep their relative order after the listed ones
+            let order: Vec<Uuid> = payload
+                .uuids
+                .iter()
+                .chain(items.iter())
+                .filter(|uuid| known.contains(uuid) && seen.insert(**uuid))
+                .copied()
+                .collect();
+
+            for (position, uuid) in order.iter().enumerate() {
+                playlist_item::Entity::update_many()
+                    .col_expr(
+                        playlist_item::Column::Position,
+                        Expr::value(position as i32),
+                    )
+                    .filter(playlist_item::Column::PlaylistId.eq(playlist.id))
+                    .filter(playlist_item::Column::Uuid.eq(*uuid))
+                    .exec(&transaction)
+                    .await
+                    .map_err(|_| ErrorInternalServerError("Unable to reorder the playlist"))?;
+            }
+
+            transaction
+                .commit()
+                .await
+                .map_err(|_| ErrorInternalServerError("Unable to reorder the playlist"))?;
+            touch_playlist(playlist, &data).await?;
+
+            Ok(HttpResponse::Ok().finish())
+        }
+    }
+
+    pub mod video {
+        use super::*;
+
+        pub mod uuid {
+            use super::*;
+
+            #[derive(Deserialize, Validate, Debug)]
+            struct PlaylistVideo {
+                id: Uuid,
+                uuid: Uuid,
+            }
+
+            #[put("/playlist/{id}/video/{uuid}")]
+            async fn put(
+                request: HttpRequest,
+                params: Path<PlaylistVideo>,
+                data: Data<AppState<'_>>,
+            ) -> actix_web::Result<impl Responder> {
+                let Some(jwt) = get_authentication_data(&request, &data).await else {
+                    return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+                };
+
+                let playlist = find_playlist(&params.id, &data.db_connection).await?;
+
+                if playlist.user_id != jwt.sub {
+                    return Ok(
+                        HttpResponse::Forbidden().body("You cannot edit another user's playlist")
+                    );
+                }
+
+                find_visible_video(&params.uuid, &data.db_connection).await?;
+
+                let transaction = data.db_connection.begin().await.map_err(|_| {
+                    ErrorInternalServerError("Unable to add the video to the playlist")
+                })?;
+
+                add_video(&playlist.id, &params.uuid, &transaction).await?;
+                transaction.commit().await.map_err(|_| {
+                    ErrorInternalServerError("Unable to add the video to the playlist")
+                })?;
+                touch_playlist(playlist, &data).await?;
+
+                Ok(HttpResponse::Ok().finish())
+            }
+
+            #[delete("/playlist/{id}/video/{uuid}")]
+            async fn delete(
+                request: HttpRequest,
+                params: Path<PlaylistVideo>,
+                data: Data<AppState<'_>>,
+            ) -> actix_web::Result<impl Responder> {
+                let Some(jwt) = get_authentication_data(&request, &data).await else {
+                    return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+                };
+
+                let playlist = find_playlist(&params.id, &data.db_connection).await?;
+
+                if playlist.user_id != jwt.sub {
+                    return Ok(
+                        HttpResponse::Forbidden().body("You cannot edit another user's playlist")
+                    );
+                }
+
+                playlist_item::Entity::delete_by_id((playlist.id, params.uuid))
+                    .exec(&data.db_connection)
+                    .await
+                    .map_err(|_| {
+                        ErrorInternalServerError("Unable to remove the video from the playlist")
+                    })?;
+                touch_playlist(playlist, &data).await?;
+
+                Ok(HttpResponse::Ok().finish())
+            }
+        }
+    }
+}
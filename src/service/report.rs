@@ -0,0 +1,93 @@
+use std::str::FromStr;
+
+use ::uuid::Uuid;
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    post,
+    web::Data,
+    HttpRequest, HttpResponse, Responder,
+};
+use actix_web_validator5::Json;
+use sea_orm::{sea_query::OnConflict, EntityTrait, Set};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::{
+    entity::{report, sea_orm_active_enums::ReportTargetType},
+    util::{comment::find_comment, get_authentication_data, video::find_visible_video},
+    AppState,
+};
+
+#[derive(Deserialize, Validate, Debug)]
+struct PostReport {
+    target_type: ReportTargetType,
+    #[validate(length(min = 1, max = 36))]
+    target_id: String,
+    #[validate(length(min = 1, max = 1000))]
+    reason: String,
+}
+
+#[post("/report")]
+async fn post(
+    request: HttpRequest,
+    payload: Json<PostReport>,
+    data: Data<AppState<'_>>,
+) -> actix_web::Result<impl Responder> {
+    let Some(jwt) = get_authentication_data(&request, &data).await else {
+        return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+    };
+
+    let reason = payload.reason.trim();
+
+    if reason.is_empty() {
+        return Err(ErrorBadRequest("The reason is empty"));
+    }
+
+    // the target id is normalized so reports on the same target are grouped together
+    let target_id = match payload.target_type {
+        ReportTargetType::Video => {
+            let uuid = Uuid::from_str(&payload.target_id)
+                .map_err(|_| ErrorBadRequest("Invalid video uuid"))?;
+
+            find_visible_video(&uuid, &data.db_connection)
+                .await?
+                .uuid
+                .to_string()
+        }
+        ReportTargetType::Comment => {
+            let id = payload
+                .target_id
+                .parse::<i64>()
+                .map_err(|_| ErrorBadRequest("Invalid comment id"))?;
+
+            find_comment(id, &data.db_connection).await?.id.to_string()
+        }
+    };
+
+    report::Entity::insert(report::ActiveModel {
+        target_type: Set(payload.target_type),
+        target_id: Set(target_id),
+        reason: Set(reason.to_string()),
+        reporter_id: Set(jwt.sub),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([
+            report::Column::TargetType,
+            report::Column::TargetId,
+            report::Column::ReporterId,
+        ])
+        // reporting the same target again reopens the report
+        .update_columns([
+            report::Column::Reason,
+            report::Column::Timestamp,
+            report::Column::Resolved,
+        ])
+        .to_owned(),
+    )
+    .exec_without_returning(&data.db_connection)
+    .await
+    .map_err(|_| ErrorInternalServerError("Unable to add report"))?;
+
+    Ok(HttpResponse::Ok().finish())
+}
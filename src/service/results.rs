@@ -1,18 +1,123 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
 use actix_web::{error::ErrorInternalServerError, get, web::Data, HttpResponse, Responder};
 use actix_web_validator5::Query;
-use futures::future::join_all;
-use serde::Deserialize;
+use chrono::Utc;
+use fred::{
+    interfaces::KeysInterface,
+    types::{Expiration, RedisValue},
+};
+use meilisearch_sdk::search::Selectors;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use validator::Validate;
 
-use crate::{util::channel::get_channel_info, AppState, MeilliDocument};
+use crate::{
+    util::channel::{get_channel_info, get_channels_info},
+    AppState, MeilliDocument,
+};
+
+const CHANNEL_RESULTS_LIMIT: usize = 3;
+const PLAYLIST_RESULTS_LIMIT: usize = 5;
+const RESULTS_DEFAULT_LIMIT: usize = 20;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum UploadDate {
+    Hour,
+    Today,
+    Week,
+    Month,
+    Year,
+    #[default]
+    #[serde(other)]
+    Any,
+}
+
+impl UploadDate {
+    fn filter(self) -> Option<String> {
+        let seconds = match self {
+            UploadDate::Hour => 3600,
+            UploadDate::Today => 86400,
+            UploadDate::Week => 7 * 86400,
+            UploadDate::Month => 30 * 86400,
+            UploadDate::Year => 365 * 86400,
+            UploadDate::Any => return None,
+        };
+
+        Some(format!(
+            "timestamp_unix >= {}",
+            Utc::now().timestamp() - seconds
+        ))
+    }
+}
+
+/// Short is under 4 minutes, long over 20 minutes.
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum DurationBucket {
+    Short,
+    Medium,
+    Long,
+    #[default]
+    #[serde(other)]
+    Any,
+}
+
+impl DurationBucket {
+    fn filter(self) -> Option<String> {
+        match self {
+            DurationBucket::Short => Some("duration < 240".to_string()),
+            DurationBucket::Medium => Some("duration 240 TO 1200".to_string()),
+            DurationBucket::Long => Some("duration > 1200".to_string()),
+            DurationBucket::Any => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum ResultsSort {
+    Date,
+    Views,
+    Likes,
+    #[default]
+    #[serde(other)]
+    Relevance,
+}
+
+impl ResultsSort {
+    fn sort(self) -> Option<&'static str> {
+        match self {
+            ResultsSort::Date => Some("timestamp_unix:desc"),
+            ResultsSort::Views => Some("views:desc"),
+            ResultsSort::Likes => Some("likes:desc"),
+            ResultsSort::Relevance => None,
+        }
+    }
+}
+
+fn filter_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
 
 #[derive(Deserialize, Validate, Debug)]
 struct GetResults {
     q: String,
+    #[validate(range(min = 1, max = 100))]
     l: Option<usize>,
+    #[validate(range(max = 1000))]
+    page: Option<usize>,
+    #[serde(default)]
+    date: UploadDate,
+    #[serde(default)]
+    duration: DurationBucket,
+    #[validate(length(max = 100))]
+    tag: Option<String>,
+    #[validate(length(max = 32))]
+    channel: Option<String>,
+    #[serde(default)]
+    sort: ResultsSort,
 }
 
 #[get("/results")]
@@ -20,54 +125,132 @@ async fn get(
     query: Query<GetResults>,
     data: Data<AppState<'_>>,
 ) -> actix_web::Result<impl Responder> {
-    let results = data
-        .video_index
-        .search()
+    let limit = query.l.unwrap_or(RESULTS_DEFAULT_LIMIT);
+    let page = query.page.unwrap_or_default();
+    let tag = query
+        .tag
+        .as_deref()
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty());
+    let channel = query
+        .channel
+        .as_deref()
+        .filter(|channel| !channel.is_empty());
+    let filters: Vec<String> = [
+        query.date.filter(),
+        query.duration.filter(),
+        tag.map(|tag| format!("tags = {}", filter_string(tag))),
+        channel.map(|channel| format!("user_id = {}", filter_string(channel))),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let sort = query.sort.sort();
+    let mut search = data.video_index.search();
+
+    search
         .with_query(&query.q)
-        .with_limit(query.l.unwrap_or(20))
-        .with_attributes_to_search_on(&["title", "tags", "description"])
+        .with_offset(page * limit)
+        // one more to know if there is a next page
+        .with_limit(limit + 1)
+        .with_attributes_to_search_on(&["title", "tags", "description"]);
+
+    if !filters.is_empty() {
+        search.with_array_filter(filters.iter().map(String::as_str).collect());
+    }
+
+    if sort.is_some() {
+        search.with_sort(sort.as_slice());
+    }
+
+    let results = search
         .execute::<MeilliDocument>()
         .await
         .map_err(|_| ErrorInternalServerError("Unable to search the query"))?;
+    // channels and playlists only come with the first page of an unfiltered search
+    let channels = if page == 0 && filters.is_empty() {
+        data.channel_index
+            .search()
+            .with_query(&query.q)
+            .with_limit(CHANNEL_RESULTS_LIMIT)
+            .execute::<MeilliDocument>()
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to search the query"))?
+            .hits
+    } else {
+        Vec::new()
+    };
+    let playlists = if page == 0 && filters.is_empty() {
+        data.playlist_index
+            .search()
+            .with_query(&query.q)
+            .with_limit(PLAYLIST_RESULTS_LIMIT)
+            .with_attributes_to_search_on(&["title", "description"])
+            .execute::<MeilliDocument>()
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to search the query"))?
+            .hits
+            .iter()
+            .map(|result| serde_json::to_value(&result.result).unwrap())
+            .collect::<Vec<Value>>()
+    } else {
+        Vec::new()
+    };
     let mut results = results
         .hits
         .iter()
         .map(|result| serde_json::to_value(&result.result).unwrap())
         .collect::<Vec<Value>>();
-    let user_ids: HashSet<String> = results
+    let has_next_page = results.len() > limit;
+
+    results.truncate(limit);
+
+    let channel_info = match channel {
+        Some(channel) => Some(get_channel_info(channel, &data.clerk, &data.redis_client).await?),
+        None => None,
+    };
+    let mut user_ids: HashSet<String> = results
         .iter()
-        .map(|result| {
-            result.as_object().unwrap()["user_id"]
-                .as_str()
-                .unwrap()
-                .to_string()
-        })
+        .chain(playlists.iter())
+        .filter_map(|result| Some(result.get("user_id")?.as_str()?.to_string()))
         .collect();
-    let mut channels_info = HashMap::new();
 
-    for channel_info in join_all(
-        user_ids
-            .iter()
-            .map(|user_id| get_channel_info(&user_id, &data.clerk, &data.redis_client)),
-    )
-    .await
-    {
-        let channel_info = channel_info?;
-
-        channels_info.insert(
-            channel_info.user_id.clone(),
-            serde_json::to_value(channel_info).unwrap(),
-        );
-    }
+    user_ids.extend(channels.iter().map(|channel| channel.result.id.clone()));
 
-    for result in results.iter_mut() {
-        let result_object = result.as_object_mut().unwrap();
+    let channels_info = get_channels_info(&user_ids, &data.clerk, &data.redis_client).await;
+    let results: Vec<Value> = results
+        .into_iter()
+        .filter_map(|mut result| {
+            // a partial document can't be rendered, it is left out until the next reindex
+            result.get("title")?;
 
-        result_object.insert(
-            "channel_info".to_string(),
-            channels_info[result_object["user_id"].as_str().unwrap()].clone(),
-        );
-    }
+            let channel_info = channels_info.get(result.get("user_id")?.as_str()?)?;
+
+            result["channel_info"] = json!(channel_info);
+
+            Some(result)
+        })
+        .collect();
+    let playlists: Vec<Value> = playlists
+        .into_iter()
+        .filter_map(|mut playlist| {
+            let channel_info = channels_info.get(playlist.get("user_id")?.as_str()?)?;
+
+            playlist["channel_info"] = json!(channel_info);
+
+            Some(playlist)
+        })
+        .collect();
+    let channels: Vec<Value> = channels
+        .iter()
+        .filter_map(|channel| {
+            Some(json!({
+                "description": channel.result.value["description"],
+                "subscribers": channel.result.value["subscribers"],
+                "channel_info": channels_info.get(&channel.result.id)?,
+            }))
+        })
+        .collect();
 
     Ok(HttpResponse::Ok()
         .insert_header(("Cache-Control", "no-store"))
@@ -79,8 +262,153 @@ async fn get(
                     &json!({
                         "search": query.q,
                         "results": results,
+                        "channels": channels,
+                        "playlists": playlists,
+                        "filters": {
+                            "date": query.date,
+                            "duration": query.duration,
+                            "tag": tag,
+                            "channel": channel,
+                            "sort": query.sort,
+                        },
+                        "channel_info": channel_info,
+                        "has_previous_page": page > 0,
+                        "has_next_page": has_next_page,
+                        "previous_page": page.saturating_sub(1),
+                        "next_page": page + 1,
                     }),
                 )
                 .unwrap(),
         ))
 }
+
+pub mod suggestions {
+    use super::*;
+
+    const SUGGESTIONS_TITLE_LIMIT: usize = 5;
+    const SUGGESTIONS_TAG_LIMIT: usize = 5;
+    const SUGGESTIONS_CHANNEL_LIMIT: usize = 3;
+    const SUGGESTIONS_REDIS_TIMEOUT: i64 = 60;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct GetSuggestions {
+        #[validate(length(min = 1, max = 100))]
+        q: String,
+    }
+
+    async fn find_suggestions(prefix: &str, data: &AppState<'_>) -> actix_web::Result<Value> {
+        let results = data
+            .video_index
+            .search()
+            .with_query(prefix)
+            .with_limit(SUGGESTIONS_TITLE_LIMIT)
+            .with_attributes_to_search_on(&["title", "tags"])
+            .with_attributes_to_retrieve(Selectors::Some(&["id", "title"]))
+            .with_attributes_to_highlight(Selectors::Some(&["title"]))
+            .with_highlight_pre_tag("<mark>")
+            .with_highlight_post_tag("</mark>")
+            .with_facets(Selectors::Some(&["tags"]))
+            .execute::<MeilliDocument>()
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to search the query"))?;
+        let titles: Vec<Value> = results
+            .hits
+            .iter()
+            .map(|hit| {
+                json!({
+                    "uuid": hit.result.id,
+                    "title": hit.result.value["title"],
+                    "highlight": hit
+                        .formatted_result
+                        .as_ref()
+                        .and_then(|formatted_result| formatted_result.get("title")),
+                })
+            })
+            .collect();
+        // only the word being typed is completed against the tags
+        let word = prefix.split_whitespace().last().unwrap_or_default();
+        let mut tags: Vec<(&String, &usize)> = results
+            .facet_distribution
+            .as_ref()
+            .and_then(|facet_distribution| facet_distribution.get("tags"))
+            .map(|tags| {
+                tags.iter()
+                    .filter(|(tag, _)| tag.to_lowercase().starts_with(word))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        tags.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        tags.truncate(SUGGESTIONS_TAG_LIMIT);
+
+        // channels come from their own index, so typing never waits on Clerk
+        let channels: Vec<Value> = data
+            .channel_index
+            .search()
+            .with_query(prefix)
+            .with_limit(SUGGESTIONS_CHANNEL_LIMIT)
+            .with_attributes_to_search_on(&["username"])
+            .execute::<MeilliDocument>()
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to search the query"))?
+            .hits
+            .iter()
+            .map(|hit| {
+                json!({
+                    "user_id": hit.result.id,
+                    "username": hit.result.value["username"],
+                    "profil_picture": hit.result.value["profil_picture"],
+                })
+            })
+            .collect();
+
+        Ok(json!({
+            "titles": titles,
+            "tags": tags
+                .into_iter()
+                .map(|(tag, count)| json!({ "tag": tag, "count": count }))
+                .collect::<Vec<Value>>(),
+            "channels": channels,
+        }))
+    }
+
+    #[get("/results/suggestions")]
+    async fn get(
+        query: Query<GetSuggestions>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let prefix = query.q.trim().to_lowercase();
+
+        if prefix.is_empty() {
+            return Ok(HttpResponse::Ok()
+                .insert_header(("Cache-Control", "no-store"))
+                .json(json!({ "titles": [], "tags": [], "channels": [] })));
+        }
+
+        let key = format!("suggestions:{prefix}");
+        let suggestions = match data.redis_client.get::<Option<String>, _>(&key).await {
+            Ok(Some(suggestions)) => suggestions,
+            _ => {
+                let suggestions = find_suggestions(&prefix, &data).await?.to_string();
+
+                data.redis_client
+                    .set::<RedisValue, _, _>(
+                        &key,
+                        suggestions.as_str(),
+                        Some(Expiration::EX(SUGGESTIONS_REDIS_TIMEOUT)),
+                        None,
+                        false,
+                    )
+                    .await
+                    .ok();
+
+                suggestions
+            }
+        };
+
+        Ok(HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .insert_header(("Content-type", "application/json"))
+            .body(suggestions))
+    }
+}
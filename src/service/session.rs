@@ -0,0 +1,41 @@
+use actix_web::{delete, web::Data, HttpRequest, HttpResponse, Responder};
+use actix_web_validator5::Path;
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::{
+    util::session::{invalidate_session, read_session_owner_cookie, session_owner_removal_cookie},
+    AppState,
+};
+
+pub mod sid {
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct Session {
+        #[validate(length(min = 1, max = 64))]
+        sid: String,
+    }
+
+    /// Called by the page on sign out, it only forgets the cached status of the session.
+    /// Clerk has already cleared `__session` by then, the signed owner cookie proves the session was this browser's.
+    #[delete("/session/{sid}")]
+    async fn delete(
+        request: HttpRequest,
+        params: Path<Session>,
+        data: Data<AppState<'_>>,
+    ) -> impl Responder {
+        if read_session_owner_cookie(&request, &data.visitor_key).as_deref()
+            != Some(params.sid.as_str())
+        {
+            return HttpResponse::Forbidden().body("This session belongs to another browser");
+        }
+
+        invalidate_session(&params.sid, &data.redis_client).await;
+
+        HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .cookie(session_owner_removal_cookie())
+            .finish()
+    }
+}
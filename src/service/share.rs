@@ -4,11 +4,25 @@ use ::uuid::Uuid;
 use actix_web::{post, web::Data, HttpRequest, HttpResponse, Responder};
 use actix_web_validator5::Path;
 use chrono::{DateTime, Utc};
+use fred::{
+    interfaces::KeysInterface,
+    types::{Expiration, SetOptions},
+};
 use gorse_rs::Feedback;
 use serde::Deserialize;
 use validator::Validate;
 
-use crate::{util::get_authentication_data, AppState};
+use crate::{
+    util::{
+        get_authentication_data,
+        trending::{record_engagement, Engagement},
+        visitor::get_visitor_id,
+    },
+    AppState,
+};
+
+/// Sharing the same video again within this window doesn't count.
+const SHARE_DEDUPE_TIMEOUT: i64 = 3600;
 
 pub mod uuid {
     use super::*;
@@ -24,7 +38,31 @@ pub mod uuid {
         params: Path<PostShare>,
         data: Data<AppState<'_>>,
     ) -> actix_web::Result<impl Responder> {
-        if let Some(jwt) = get_authentication_data(&request, &data.clerk).await {
+        let jwt = get_authentication_data(&request, &data).await;
+        let identity = match (&jwt, get_visitor_id(&request)) {
+            (Some(jwt), _) => jwt.sub.clone(),
+            (None, Some(visitor_id)) => visitor_id.gorse_user_id(),
+            (None, None) => return Ok(HttpResponse::Unauthorized().body("Visitor not identified")),
+        };
+        let first_share = data
+            .redis_client
+            .set::<Option<String>, _, _>(
+                format!("share:{identity}:{}", params.uuid),
+                1,
+                Some(Expiration::EX(SHARE_DEDUPE_TIMEOUT)),
+                Some(SetOptions::NX),
+                false,
+            )
+            .await
+            .is_ok_and(|reply| reply.is_some());
+
+        if !first_share {
+            return Ok(HttpResponse::Ok().finish());
+        }
+
+        record_engagement(&params.uuid, Engagement::Share, &data.redis_client).await;
+
+        if let Some(jwt) = jwt {
             data.gorse_client
                 .insert_feedback(&vec![Feedback {
                     feedback_type: "share".to_string(),
@@ -36,6 +74,6 @@ pub mod uuid {
                 .ok();
         }
 
-        Ok(HttpResponse::Ok())
+        Ok(HttpResponse::Ok().finish())
     }
 }
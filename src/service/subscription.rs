@@ -0,0 +1,103 @@
+use actix_web::{
+    delete, error::ErrorInternalServerError, post, web::Data, HttpRequest, HttpResponse, Responder,
+};
+use actix_web_validator5::Path;
+use sea_orm::{sea_query::OnConflict, ColumnTrait, EntityTrait, QueryFilter, Set};
+use serde::Deserialize;
+use serde_json::json;
+use validator::Validate;
+
+use crate::{
+    entity::subscription,
+    util::{
+        channel::{get_channel_info, update_channel_index},
+        get_authentication_data,
+        labels::update_user_labels,
+        subscription::count_subscribers,
+    },
+    AppState,
+};
+
+async fn subscription_state(
+    channel_id: &str,
+    subscribed: bool,
+    data: &Data<AppState<'_>>,
+) -> actix_web::Result<HttpResponse> {
+    let subscribers = count_subscribers(channel_id, &data.db_connection).await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(json!({
+            "subscribers": subscribers,
+            "subscribed": subscribed,
+        })))
+}
+
+pub mod channel_id {
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug)]
+    struct Subscription {
+        #[validate(length(min = 1, max = 32))]
+        channel_id: String,
+    }
+
+    #[post("/subscription/{channel_id}")]
+    async fn post(
+        request: HttpRequest,
+        params: Path<Subscription>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        if params.channel_id == jwt.sub {
+            return Ok(HttpResponse::BadRequest().body("You cannot subscribe to yourself"));
+        }
+
+        get_channel_info(&params.channel_id, &data.clerk, &data.redis_client).await?;
+        subscription::Entity::insert(subscription::ActiveModel {
+            user_id: Set(jwt.sub.clone()),
+            channel_id: Set(params.channel_id.clone()),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::columns([
+                subscription::Column::UserId,
+                subscription::Column::ChannelId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to subscribe"))?;
+        update_user_labels(&jwt.sub, &data).await.ok();
+        update_channel_index(&params.channel_id, &data).await.ok();
+
+        subscription_state(&params.channel_id, true, &data).await
+    }
+
+    #[delete("/subscription/{channel_id}")]
+    async fn delete(
+        request: HttpRequest,
+        params: Path<Subscription>,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        subscription::Entity::delete_many()
+            .filter(subscription::Column::UserId.eq(&jwt.sub))
+            .filter(subscription::Column::ChannelId.eq(&params.channel_id))
+            .exec(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to unsubscribe"))?;
+        update_user_labels(&jwt.sub, &data).await.ok();
+        update_channel_index(&params.channel_id, &data).await.ok();
+
+        subscription_state(&params.channel_id, false, &data).await
+    }
+}
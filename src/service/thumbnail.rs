@@ -1,10 +1,12 @@
 use ::uuid::Uuid;
 use actix_files::NamedFile;
-use actix_web::{error::ErrorInternalServerError, get, HttpRequest, Responder};
+use actix_web::{error::ErrorInternalServerError, get, web::Data, HttpRequest, Responder};
 use actix_web_validator5::Path;
 use serde::Deserialize;
 use validator::Validate;
 
+use crate::{util::video::find_visible_video, AppState};
+
 pub mod uuid {
     use super::*;
 
@@ -17,7 +19,10 @@ pub mod uuid {
     async fn get(
         request: HttpRequest,
         params: Path<GetThumbnail>,
+        data: Data<AppState<'_>>,
     ) -> actix_web::Result<impl Responder> {
+        find_visible_video(&params.uuid, &data.db_connection).await?;
+
         Ok(NamedFile::open(format!("./thumbnail/{}.webp", params.uuid))
             .map(|file| file.use_etag(false).use_last_modified(false))
             .map_err(|_| ErrorInternalServerError("Unable to open the file"))?
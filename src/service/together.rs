@@ -1,11 +1,75 @@
+use std::time::Duration;
+
 use ::uuid::Uuid;
-use actix_web::{get, web::Data, HttpResponse, Responder};
-use actix_web_validator5::Path;
+use actix_http::ws::Frame;
+use actix_web::{
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorNotFound},
+    get, post,
+    web::{Data, Payload},
+    HttpRequest, HttpResponse, Responder,
+};
+use actix_web_validator5::{Json, Path};
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{json, Value};
+use tokio::{sync::broadcast::error::RecvError, time::interval};
 use validator::Validate;
 
-use crate::AppState;
+use crate::{
+    entity::sea_orm_active_enums::VideoUploadState,
+    util::{
+        get_authentication_data,
+        together::{
+            allow_message, ban_identity, create_room, get_chat_messages, get_member, get_members,
+            get_room, is_banned, is_muted, leave_room, now_millis, publish_member_event,
+            publish_together_event, push_chat_message, remove_identity, remove_stale_members,
+            set_muted, set_room, touch_member, update_members, ChatMessage, Member, Room,
+            TogetherCommand, TogetherEvent, TOGETHER_CHAT_MAX_LENGTH, TOGETHER_REACTIONS,
+        },
+        video::{find_visible_video, get_resolutions, get_stream_lengths},
+        visitor::get_visitor_id,
+        websocket::{start, WebSocket, WebSocketStream},
+    },
+    AppState,
+};
+
+const TOGETHER_HEARTBEAT: Duration = Duration::from_secs(10);
+const TOGETHER_MIN_RATE: f64 = 0.25;
+const TOGETHER_MAX_RATE: f64 = 4.;
+const TOGETHER_RATE_WINDOW: i64 = 10;
+/// Messages allowed per member over the rate window.
+const TOGETHER_CHAT_LIMIT: i64 = 5;
+const TOGETHER_REACTION_LIMIT: i64 = 10;
+
+#[derive(Deserialize, Validate, Debug)]
+struct PostTogether {
+    video: Uuid,
+    #[validate(range(min = 0.))]
+    position: f64,
+}
+
+#[post("/together")]
+async fn post(
+    request: HttpRequest,
+    payload: Json<PostTogether>,
+    data: Data<AppState<'_>>,
+) -> actix_web::Result<impl Responder> {
+    let Some(member) = get_member(&request, &data).await else {
+        return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+    };
+
+    find_visible_video(&payload.video, &data.db_connection).await?;
+
+    let room_id = create_room(
+        payload.video,
+        member.identity,
+        payload.position,
+        &data.redis_client,
+    )
+    .await
+    .map_err(|_| ErrorInternalServerError("Unable to create the room"))?;
+
+    Ok(HttpResponse::Ok().json(json!({ "room": room_id })))
+}
 
 pub mod uuid {
     use super::*;
@@ -17,9 +81,31 @@ pub mod uuid {
 
     #[get("/together/{uuid}")]
     async fn get(
+        request: HttpRequest,
         params: Path<GetTogether>,
         data: Data<AppState<'_>>,
     ) -> actix_web::Result<impl Responder> {
+        let jwt = get_authentication_data(&request, &data).await;
+        let room = get_room(&params.uuid, &data.redis_client)
+            .await
+            .ok_or_else(|| ErrorNotFound("Unable to find this room"))?;
+
+        let identity = match &jwt {
+            Some(jwt) => Some(jwt.sub.clone()),
+            None => get_visitor_id(&request).map(|visitor_id| visitor_id.gorse_user_id()),
+        };
+
+        if let Some(identity) = identity {
+            if is_banned(&params.uuid, &identity, &data.redis_client).await {
+                return Err(ErrorForbidden("You were removed from this room"));
+            }
+        }
+
+        let video = find_visible_video(&room.video, &data.db_connection).await?;
+        let resolutions =
+            get_resolutions(&video, VideoUploadState::eq, VideoUploadState::Available);
+        let (lengths, bitrates) = get_stream_lengths(&video, &resolutions).await?;
+
         Ok(HttpResponse::Ok()
             .insert_header(("Cache-Control", "no-store"))
             .insert_header(("Content-type", "text/html; charset=utf-8"))
@@ -28,10 +114,444 @@ pub mod uuid {
                     .render(
                         "together",
                         &json!({
-                            "uuid": params.uuid
+                            "room": params.uuid,
+                            "uuid": video.uuid,
+                            "title": video.title,
+                            "duration": video.duration,
+                            "framerate": video.framerate,
+                            "resolutions": resolutions,
+                            "lengths": lengths,
+                            "bitrates": bitrates,
+                            "has_audio": video.has_audio,
+                            "logged_in": jwt.is_some(),
+                            "reactions": TOGETHER_REACTIONS,
+                            "chat_max_length": TOGETHER_CHAT_MAX_LENGTH,
+                            "ice_servers": serde_json::to_string(&data.ice_servers).unwrap(),
                         }),
                     )
                     .unwrap(),
             ))
     }
+
+    pub mod ws {
+        use super::*;
+
+        fn send_event(socket: &WebSocket, event: &TogetherEvent) -> bool {
+            serde_json::to_string(event).is_ok_and(|event| socket.text(event))
+        }
+
+        fn valid_position(position: f64) -> bool {
+            position.is_finite() && position >= 0.
+        }
+
+        async fn set_voice(
+            room_id: &Uuid,
+            member: &mut Member,
+            enabled: bool,
+            data: &AppState<'_>,
+        ) -> anyhow::Result<()> {
+            if !member.authenticated || member.voice == enabled {
+                return Ok(());
+            }
+
+            member.voice = enabled;
+
+            touch_member(room_id, member, &data.redis_client).await?;
+            update_members(room_id, &data.redis_client).await
+        }
+
+        /// Session descriptions and candidates only travel between authenticated members of the room in the voice channel.
+        async fn relay_signal(
+            room_id: &Uuid,
+            member: &Member,
+            to: Uuid,
+            signal: Value,
+            data: &AppState<'_>,
+        ) -> anyhow::Result<()> {
+            if !member.voice {
+                return Ok(());
+            }
+
+            let members = get_members(room_id, &data.redis_client).await?;
+
+            if members
+                .iter()
+                .any(|target| target.id == to && target.authenticated && target.voice)
+            {
+                publish_member_event(
+                    room_id,
+                    &to,
+                    &TogetherEvent::Signal {
+                        from: member.id,
+                        signal,
+                    },
+                    &data.redis_client,
+                )
+                .await;
+            }
+
+            Ok(())
+        }
+
+        async fn send_chat(
+            room_id: &Uuid,
+            member: &Member,
+            text: &str,
+            socket: &WebSocket,
+            data: &AppState<'_>,
+        ) -> anyhow::Result<()> {
+            let text = text.trim();
+
+            if text.is_empty() || text.chars().count() > TOGETHER_CHAT_MAX_LENGTH {
+                return Ok(());
+            }
+
+            if is_muted(room_id, &member.identity, &data.redis_client).await {
+                send_event(socket, &TogetherEvent::Refused { reason: "muted" });
+
+                return Ok(());
+            }
+
+            if !allow_message(
+                room_id,
+                &member.identity,
+                "chat",
+                TOGETHER_CHAT_LIMIT,
+                TOGETHER_RATE_WINDOW,
+                &data.redis_client,
+            )
+            .await
+            {
+                send_event(
+                    socket,
+                    &TogetherEvent::Refused {
+                        reason: "rate_limited",
+                    },
+                );
+
+                return Ok(());
+            }
+
+            let message = ChatMessage {
+                member_id: member.id,
+                username: member.username.clone(),
+                profil_picture: member.profil_picture.clone(),
+                text: text.to_string(),
+                timestamp: now_millis(),
+            };
+
+            push_chat_message(room_id, &message, &data.redis_client).await?;
+            publish_together_event(room_id, &TogetherEvent::Chat(message), &data.redis_client)
+                .await;
+
+            Ok(())
+        }
+
+        /// Reactions are not kept in the backlog, they only make sense live.
+        async fn send_reaction(
+            room_id: &Uuid,
+            member: &Member,
+            emoji: String,
+            socket: &WebSocket,
+            data: &AppState<'_>,
+        ) -> anyhow::Result<()> {
+            if !TOGETHER_REACTIONS.contains(&emoji.as_str()) {
+                return Ok(());
+            }
+
+            if is_muted(room_id, &member.identity, &data.redis_client).await {
+                send_event(socket, &TogetherEvent::Refused { reason: "muted" });
+
+                return Ok(());
+            }
+
+            if !allow_message(
+                room_id,
+                &member.identity,
+                "reaction",
+                TOGETHER_REACTION_LIMIT,
+                TOGETHER_RATE_WINDOW,
+                &data.redis_client,
+            )
+            .await
+            {
+                return Ok(());
+            }
+
+            publish_together_event(
+                room_id,
+                &TogetherEvent::Reaction {
+                    member_id: member.id,
+                    username: member.username.clone(),
+                    emoji,
+                },
+                &data.redis_client,
+            )
+            .await;
+
+            Ok(())
+        }
+
+        /// Muting applies to every connection of the member, the host can't be muted.
+        async fn mute_member(
+            room_id: &Uuid,
+            room: &Room,
+            member_id: &Uuid,
+            muted: bool,
+            data: &AppState<'_>,
+        ) -> anyhow::Result<()> {
+            let members = get_members(room_id, &data.redis_client).await?;
+            let Some(target) = members
+                .iter()
+                .find(|member| member.id == *member_id && member.identity != room.host)
+            else {
+                return Ok(());
+            };
+
+            set_muted(room_id, &target.identity, muted, &data.redis_client).await?;
+            update_members(room_id, &data.redis_client).await
+        }
+
+        async fn kick_member(
+            room_id: &Uuid,
+            room: &Room,
+            member_id: &Uuid,
+            data: &AppState<'_>,
+        ) -> anyhow::Result<()> {
+            let members = get_members(room_id, &data.redis_client).await?;
+            let Some(target) = members
+                .iter()
+                .find(|member| member.id == *member_id && member.identity != room.host)
+            else {
+                return Ok(());
+            };
+
+            ban_identity(room_id, &target.identity, &data.redis_client).await?;
+
+            for member_id in remove_identity(room_id, &target.identity, &data.redis_client).await? {
+                publish_member_event(
+                    room_id,
+                    &member_id,
+                    &TogetherEvent::Kicked,
+                    &data.redis_client,
+                )
+                .await;
+            }
+
+            update_members(room_id, &data.redis_client).await
+        }
+
+        /// Only the host moves the playback, anyone else is sent back the current state.
+        async fn handle_command(
+            room_id: &Uuid,
+            member: &mut Member,
+            command: TogetherCommand,
+            socket: &WebSocket,
+            data: &AppState<'_>,
+        ) -> anyhow::Result<()> {
+            let now = now_millis();
+            let command = match command {
+                TogetherCommand::Ping { client_time } => {
+                    send_event(
+                        socket,
+                        &TogetherEvent::Pong {
+                            client_time,
+                            server_time: now,
+                        },
+                    );
+
+                    return Ok(());
+                }
+                command => command,
+            };
+
+            // the connection is closed on the next heartbeat, until then a kicked member is ignored
+            if is_banned(room_id, &member.identity, &data.redis_client).await {
+                return Ok(());
+            }
+
+            let command = match command {
+                TogetherCommand::Voice { enabled } => {
+                    return set_voice(room_id, member, enabled, data).await
+                }
+                TogetherCommand::Signal { to, signal } => {
+                    return relay_signal(room_id, member, to, signal, data).await
+                }
+                command => command,
+            };
+
+            let Some(mut room) = get_room(room_id, &data.redis_client).await else {
+                return Ok(());
+            };
+
+            let command = match command {
+                TogetherCommand::Chat { text } => {
+                    return send_chat(room_id, member, &text, socket, data).await
+                }
+                TogetherCommand::Reaction { emoji } => {
+                    return send_reaction(room_id, member, emoji, socket, data).await
+                }
+                command => command,
+            };
+
+            if room.host != member.identity {
+                send_event(socket, &TogetherEvent::from(&room));
+
+                return Ok(());
+            }
+
+            match command {
+                TogetherCommand::Mute { member_id, muted } => {
+                    return mute_member(room_id, &room, &member_id, muted, data).await
+                }
+                TogetherCommand::Kick { member_id } => {
+                    return kick_member(room_id, &room, &member_id, data).await
+                }
+                TogetherCommand::Play { position } if valid_position(position) => {
+                    room.position = position;
+                    room.paused = false;
+                }
+                TogetherCommand::Pause { position } if valid_position(position) => {
+                    room.position = position;
+                    room.paused = true;
+                }
+                TogetherCommand::Seek { position } if valid_position(position) => {
+                    room.position = position;
+                }
+                TogetherCommand::Rate { rate }
+                    if (TOGETHER_MIN_RATE..=TOGETHER_MAX_RATE).contains(&rate) =>
+                {
+                    room.position = room.position_at(now);
+                    room.rate = rate;
+                }
+                _ => return Ok(()),
+            }
+
+            room.updated_at = now;
+
+            set_room(room_id, &room, &data.redis_client).await?;
+            publish_together_event(room_id, &TogetherEvent::from(&room), &data.redis_client).await;
+
+            Ok(())
+        }
+
+        async fn run_member(
+            room_id: Uuid,
+            mut member: Member,
+            socket: WebSocket,
+            mut stream: WebSocketStream,
+            data: Data<AppState<'static>>,
+        ) {
+            let mut events = data.together_events.subscribe();
+            let mut heartbeat = interval(TOGETHER_HEARTBEAT);
+
+            send_event(
+                &socket,
+                &TogetherEvent::Welcome {
+                    member_id: member.id,
+                },
+            );
+
+            if let Some(room) = get_room(&room_id, &data.redis_client).await {
+                send_event(&socket, &TogetherEvent::from(&room));
+            }
+
+            send_event(
+                &socket,
+                &TogetherEvent::History {
+                    messages: get_chat_messages(&room_id, &data.redis_client).await,
+                },
+            );
+            touch_member(&room_id, &mut member, &data.redis_client)
+                .await
+                .ok();
+            update_members(&room_id, &data.redis_client).await.ok();
+
+            loop {
+                tokio::select! {
+                    frame = stream.recv() => match frame {
+                        Some(Ok(Frame::Text(text))) => {
+                            if let Ok(command) = serde_json::from_slice(&text) {
+                                handle_command(&room_id, &mut member, command, &socket, &data)
+                                    .await
+                                    .ok();
+                            }
+                        }
+                        Some(Ok(Frame::Ping(bytes))) => {
+                            socket.pong(bytes);
+                        }
+                        Some(Ok(Frame::Close(_))) | Some(Err(_)) | None => break,
+                        Some(Ok(_)) => {}
+                    },
+                    event = events.recv() => match event {
+                        Ok((event_room_id, event_member_id, event))
+                            if event_room_id == room_id
+                                && event_member_id.is_none_or(|member_id| member_id == member.id) =>
+                        {
+                            if !socket.text(event) {
+                                break;
+                            }
+                        }
+                        // missed events are replaced by the current state
+                        Err(RecvError::Lagged(_)) => {
+                            if let Some(room) = get_room(&room_id, &data.redis_client).await {
+                                send_event(&socket, &TogetherEvent::from(&room));
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = heartbeat.tick() => {
+                        if is_banned(&room_id, &member.identity, &data.redis_client).await {
+                            break;
+                        }
+
+                        socket.ping();
+                        touch_member(&room_id, &mut member, &data.redis_client)
+                            .await
+                            .ok();
+
+                        if remove_stale_members(&room_id, &data.redis_client)
+                            .await
+                            .unwrap_or(false)
+                        {
+                            update_members(&room_id, &data.redis_client).await.ok();
+                        }
+                    }
+                }
+            }
+
+            socket.close();
+            leave_room(&room_id, &member.id, &data.redis_client)
+                .await
+                .ok();
+        }
+
+        #[get("/together/{uuid}/ws")]
+        async fn get(
+            request: HttpRequest,
+            params: Path<GetTogether>,
+            payload: Payload,
+            data: Data<AppState<'static>>,
+        ) -> actix_web::Result<impl Responder> {
+            get_room(&params.uuid, &data.redis_client)
+                .await
+                .ok_or_else(|| ErrorNotFound("Unable to find this room"))?;
+
+            let Some(member) = get_member(&request, &data).await else {
+                return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+            };
+
+            if is_banned(&params.uuid, &member.identity, &data.redis_client).await {
+                return Err(ErrorForbidden("You were removed from this room"));
+            }
+
+            let (response, socket, stream) = start(&request, payload)?;
+
+            // the payload is bound to the worker thread
+            actix_web::rt::spawn(run_member(params.uuid, member, socket, stream, data));
+
+            Ok(response)
+        }
+    }
 }
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use actix_web::{error::ErrorInternalServerError, get, web::Data, HttpResponse, Responder};
+use actix_web_validator5::Query;
+use sea_orm::{ColumnTrait, QueryFilter, QuerySelect};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::{
+    entity::video,
+    util::{
+        channel::get_channels_info,
+        trending::{get_trending, TRENDING_SIZE},
+        video::{find_public_videos, find_trending_videos, get_tags},
+    },
+    AppState,
+};
+
+const TRENDING_PAGE_SIZE: usize = 48;
+const TRENDING_CATEGORIES: usize = 8;
+/// Used until the first trending list is computed.
+const TRENDING_FALLBACK_DAYS: i64 = 7;
+
+#[derive(Deserialize, Validate, Debug)]
+struct GetTrending {
+    #[validate(length(max = 100))]
+    tag: Option<String>,
+}
+
+#[get("/trending")]
+async fn get(
+    query: Query<GetTrending>,
+    data: Data<AppState<'_>>,
+) -> actix_web::Result<impl Responder> {
+    let tag = query
+        .tag
+        .as_deref()
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty());
+    let trending = get_trending(&data.redis_client).await;
+    let mut videos = match trending.is_empty() {
+        true => find_trending_videos(TRENDING_FALLBACK_DAYS)
+            .limit(TRENDING_SIZE as u64)
+            .all(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to find a videos"))?,
+        false => {
+            let mut videos = find_public_videos()
+                .filter(video::Column::Uuid.is_in(trending.clone()))
+                .all(&data.db_connection)
+                .await
+                .map_err(|_| ErrorInternalServerError("Unable to find a videos"))?;
+
+            videos.sort_by_key(|video| trending.iter().position(|uuid| uuid == &video.uuid));
+            videos
+        }
+    };
+    // the categories are the most common tags among the trending videos
+    let mut tag_counts: HashMap<String, usize> = HashMap::new();
+
+    for video in &videos {
+        for video_tag in get_tags(video) {
+            *tag_counts.entry(video_tag).or_default() += 1;
+        }
+    }
+
+    let mut categories: Vec<(String, usize)> = tag_counts.into_iter().collect();
+
+    categories.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    categories.truncate(TRENDING_CATEGORIES);
+
+    if let Some(tag) = tag {
+        videos.retain(|video| get_tags(video).iter().any(|video_tag| video_tag == tag));
+    }
+
+    videos.truncate(TRENDING_PAGE_SIZE);
+
+    let channels_info = get_channels_info(
+        &videos.iter().map(|video| video.user_id.clone()).collect(),
+        &data.clerk,
+        &data.redis_client,
+    )
+    .await;
+    let videos: Vec<Value> = videos
+        .iter()
+        .filter_map(|video| {
+            Some(json!({
+                "uuid": video.uuid,
+                "title": video.title,
+                "views": video.views,
+                "timestamp": video.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                "duration": video.duration,
+                "channel_info": channels_info.get(&video.user_id)?,
+            }))
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .insert_header(("Content-type", "text/html; charset=utf-8"))
+        .body(
+            data.handlebars
+                .render(
+                    "trending",
+                    &json!({
+                        "videos": videos,
+                        "tag": tag,
+                        "categories": categories
+                            .into_iter()
+                            .map(|(category, _)| category)
+                            .collect::<Vec<String>>(),
+                    }),
+                )
+                .unwrap(),
+        ))
+}
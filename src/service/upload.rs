@@ -1,6 +1,7 @@
 use std::{
     collections::{HashMap, HashSet},
     io::SeekFrom,
+    time::Duration,
 };
 
 use ::uuid::Uuid;
@@ -10,18 +11,19 @@ use actix_web::{
     get,
     http::header,
     post, put,
-    web::{Data, Header, Payload},
+    web::{Bytes, Data, Header, Payload},
     HttpRequest, HttpResponse, Responder,
 };
 use actix_web_validator5::{Json, Path};
-use data_url::DataUrl;
 use file_format::FileFormat;
 use fred::{
     interfaces::KeysInterface,
     types::{Expiration, RedisValue},
 };
-use futures::future::{join, join3};
-use gorse_rs::Item;
+use futures::{
+    future::{join, join4},
+    stream::unfold,
+};
 use sea_orm::{
     ActiveEnum, ActiveModelTrait, ColumnTrait, EntityTrait, ModelTrait, QueryFilter, QueryOrder,
     Set,
@@ -31,33 +33,41 @@ use serde_json::{json, Value};
 use tokio::{
     fs::{create_dir_all, remove_file, OpenOptions},
     io::{AsyncSeekExt, AsyncWriteExt},
+    sync::broadcast::error::RecvError,
+    time::timeout,
 };
 use tokio_stream::StreamExt;
 use validator::Validate;
 use video::ActiveModel;
 
 use crate::{
-    entity::{sea_orm_active_enums::VideoUploadState, video},
+    entity::{
+        comment, notification, playlist_item, sea_orm_active_enums::VideoUploadState, video,
+        watch_history,
+    },
     util::{
-        get_authentication_data,
+        channel::update_channel_index,
+        decode_webp_data_url, get_authentication_data,
+        notification::{notify_upload, notify_upload_failed},
+        upload::{publish_upload_event, UploadEvent},
         video::{
-            find_video, find_video_by_resolution, get_resolutions, resolution_to_column,
-            valid_resolution, valid_resolutions, VIDEO_REDIS_TIMEOUT,
+            find_channel_videos, find_video, find_video_by_resolution, get_resolutions,
+            resolution_to_column, valid_resolution, valid_resolutions, video_to_document,
+            video_to_item, VIDEO_REDIS_TIMEOUT,
         },
     },
-    AppState, MeilliDocument,
+    AppState,
 };
 
 #[get("/upload")]
 async fn get(request: HttpRequest, data: Data<AppState<'_>>) -> actix_web::Result<impl Responder> {
-    let Some(jwt) = get_authentication_data(&request, &data.clerk).await else {
+    let Some(jwt) = get_authentication_data(&request, &data).await else {
         return Ok(HttpResponse::TemporaryRedirect()
             .insert_header(("Location", "/"))
             .finish());
     };
 
-    let videos: Vec<Value> = video::Entity::find()
-        .filter(video::Column::UserId.eq(jwt.sub))
+    let videos: Vec<Value> = find_channel_videos(&jwt.sub)
         .order_by_desc(video::Column::Timestamp)
         .all(&data.db_connection)
         .await
@@ -70,6 +80,7 @@ async fn get(request: HttpRequest, data: Data<AppState<'_>>) -> actix_web::Resul
                 "views": video.views,
                 "timestamp": video.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
                 "duration": video.duration,
+                "hidden": video.hidden,
                 "resolutions": [
                     {
                         "resolution": 144,
@@ -143,21 +154,11 @@ async fn put(
     payload: Json<PutVideo>,
     data: Data<AppState<'_>>,
 ) -> actix_web::Result<impl Responder> {
-    let Some(jwt) = get_authentication_data(&request, &data.clerk).await else {
+    let Some(jwt) = get_authentication_data(&request, &data).await else {
         return Ok(HttpResponse::Unauthorized().body("User not logged in"));
     };
 
-    let thumbnail_data_url = DataUrl::process(&payload.thumbnail)
-        .map_err(|_| ErrorInternalServerError("Unable to process thumbnail data"))?;
-    let thumbnail_data = thumbnail_data_url
-        .decode_to_vec()
-        .map_err(|_| ErrorInternalServerError("Unable to extract thumbnail data"))?
-        .0;
-
-    if FileFormat::from_bytes(&thumbnail_data).media_type() != "image/webp" {
-        return Err(ErrorInternalServerError("Wrong thumbnail mime type"));
-    }
-
+    let thumbnail_data = decode_webp_data_url(&payload.thumbnail)?;
     let has_resolution = |resolution| {
         if payload.resolutions.contains(&resolution) {
             VideoUploadState::Uploading
@@ -177,7 +178,7 @@ async fn put(
                 .filter_map(|tag| {
                     let tag = tag.trim();
 
-                    (!str::is_empty(tag)).then(|| tag)
+                    (!str::is_empty(tag)).then_some(tag)
                 })
                 .map(str::to_string)
                 .collect::<Vec<String>>()
@@ -209,7 +210,7 @@ async fn put(
         .open(format!("./thumbnail/{}.webp", uuid))
         .await
     {
-        file.write(&thumbnail_data).await.ok();
+        file.write_all(&thumbnail_data).await.ok();
     }
 
     Ok(HttpResponse::Ok().body(uuid.to_string()))
@@ -232,7 +233,7 @@ async fn delete_video(uuid: &Uuid, data: &Data<AppState<'_>>) -> actix_web::Resu
             .ok();
     }
 
-    let _ = join3(
+    let _ = join4(
         data.redis_client.del::<RedisValue, _>(
             resolutions
                 .iter()
@@ -241,9 +242,28 @@ async fn delete_video(uuid: &Uuid, data: &Data<AppState<'_>>) -> actix_web::Resu
         ),
         data.gorse_client.delete_item(&uuid.to_string()),
         video.delete(&data.db_connection),
+        comment::Entity::delete_many()
+            .filter(comment::Column::Uuid.eq(*uuid))
+            .exec(&data.db_connection),
     )
     .await;
 
+    notification::Entity::delete_many()
+        .filter(notification::Column::Uuid.eq(*uuid))
+        .exec(&data.db_connection)
+        .await
+        .ok();
+    playlist_item::Entity::delete_many()
+        .filter(playlist_item::Column::Uuid.eq(*uuid))
+        .exec(&data.db_connection)
+        .await
+        .ok();
+    watch_history::Entity::delete_many()
+        .filter(watch_history::Column::Uuid.eq(*uuid))
+        .exec(&data.db_connection)
+        .await
+        .ok();
+
     Ok(())
 }
 
@@ -278,6 +298,48 @@ async fn delete(
     })))
 }
 
+pub mod events {
+    use super::*;
+
+    const UPLOAD_EVENTS_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+    #[get("/upload/events")]
+    async fn get(
+        request: HttpRequest,
+        data: Data<AppState<'_>>,
+    ) -> actix_web::Result<impl Responder> {
+        let Some(jwt) = get_authentication_data(&request, &data).await else {
+            return Ok(HttpResponse::Unauthorized().body("User not logged in"));
+        };
+
+        let stream = unfold(
+            (data.upload_events.subscribe(), jwt.sub),
+            |(mut receiver, user_id)| async move {
+                loop {
+                    let bytes = match timeout(UPLOAD_EVENTS_KEEP_ALIVE, receiver.recv()).await {
+                        // a comment keeps the connection open through proxies
+                        Err(_) => Bytes::from_static(b": keep-alive\n\n"),
+                        Ok(Ok((event_user_id, event))) if event_user_id == user_id => {
+                            Bytes::from(format!("data: {event}\n\n"))
+                        }
+                        Ok(Ok(_)) | Ok(Err(RecvError::Lagged(_))) => continue,
+                        Ok(Err(RecvError::Closed)) => return None,
+                    };
+
+                    return Some((Ok::<_, actix_web::Error>(bytes), (receiver, user_id)));
+                }
+            },
+        );
+
+        Ok(HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .insert_header(("Content-Type", "text/event-stream"))
+            // compressing would buffer the events
+            .insert_header(header::ContentEncoding::Identity)
+            .streaming(stream))
+    }
+}
+
 pub mod uuid {
     use super::*;
 
@@ -299,7 +361,7 @@ pub mod uuid {
             data: Data<AppState<'_>>,
             range_header_option: Option<Header<header::Range>>,
         ) -> actix_web::Result<impl Responder> {
-            let Some(jwt) = get_authentication_data(&request, &data.clerk).await else {
+            let Some(jwt) = get_authentication_data(&request, &data).await else {
                 return Ok(HttpResponse::Unauthorized().body("User not logged in"));
             };
 
@@ -327,6 +389,7 @@ pub mod uuid {
             let mut file = OpenOptions::new()
                 .write(true)
                 .create(true)
+                .truncate(false)
                 .open(&path)
                 .await
                 .map_err(|_| ErrorInternalServerError("Unable to open the file"))?;
@@ -335,7 +398,7 @@ pub mod uuid {
 
             if let Some(range_header) = range_header_option {
                 if let header::Range::Bytes(range_vec) = range_header.0 {
-                    if let Some(header::ByteRangeSpec::From(starting_byte)) = range_vec.get(0) {
+                    if let Some(header::ByteRangeSpec::From(starting_byte)) = range_vec.first() {
                         file.seek(SeekFrom::Start(*starting_byte))
                             .await
                             .map_err(|_| {
@@ -352,75 +415,46 @@ pub mod uuid {
                 }
 
                 has_data = true;
-                file.write(&bytes_result?)
+                file.write_all(&bytes_result?)
                     .await
                     .map_err(|_| ErrorInternalServerError("Unable to write data"))?;
             }
 
-            if !has_data {
+            if has_data {
+                publish_upload_event(
+                    &jwt.sub,
+                    &UploadEvent::Progress {
+                        uuid: params.uuid,
+                        resolution: params.resolution,
+                        bytes: file.stream_position().await.unwrap_or_default(),
+                    },
+                    &data.redis_client,
+                )
+                .await;
+            } else {
+                // subscribers are only notified when the first resolution becomes available
+                let first_available =
+                    get_resolutions(&video, VideoUploadState::eq, VideoUploadState::Available)
+                        .is_empty();
                 let video_file_format = FileFormat::from_file(&path)
                     .map_err(|_| ErrorInternalServerError("Unable to open the file"))?;
                 let video_upload_state = if video_file_format.media_type() == "video/webm"
                     || video_file_format.media_type() == "application/x-ebml"
                 {
-                    let tags: Vec<String> = video.tags.clone().map_or(Vec::new(), |tags| {
-                        tags.split(",").map(str::to_string).collect()
-                    });
-
-                    data.video_index
-                        .add_documents(
-                            &[MeilliDocument {
-                                id: video.uuid.to_string(),
-                                value: json!({
-                                    "title": video.title,
-                                    "description": video.description,
-                                    "tags": tags,
-                                    "views": video.views,
-                                    "likes": video.likes,
-                                    "duration": video.duration,
-                                    "timestamp": video.timestamp,
-                                    "user_id": video.user_id,
-                                }),
-                            }],
-                            Some("id"),
-                        )
-                        .await
-                        .map_err(|_| {
-                            ErrorInternalServerError("Unable to add video to the search base")
-                        })?
-                        .wait_for_completion(&data.meillisearch_client, None, None)
-                        .await
-                        .map_err(|_| {
-                            ErrorInternalServerError("Unable to add video to the search base")
-                        })?;
-
-                    let mut labels: Vec<String> = tags
-                        .clone()
-                        .iter()
-                        .map(|tag| format!("tag:{tag}"))
-                        .collect();
-
-                    labels.extend_from_slice(&[
-                        format!("title:{}", video.title),
-                        format!("duration:{}", video.duration),
-                        format!("channel:{}", video.user_id),
-                    ]);
-
-                    data.gorse_client
-                        .insert_item(&Item {
-                            item_id: video.uuid.to_string(),
-                            is_hidden: false,
-                            labels: labels,
-                            categories: Vec::new(),
-                            timestamp: video.timestamp.to_string(),
-                            comment: video.description.clone().unwrap_or_default(),
-                        })
-                        .await
-                        .map_err(|_| {
-                            ErrorInternalServerError(
-                                "Unable to add video to the recommendation base",
-                            )
-                        })?;
+                    // a video taken down by an admin stays out of the search
+                    if !video.hidden {
+                        data.video_index
+                            .add_documents(&[video_to_document(&video)], Some("id"))
+                            .await
+                            .map_err(|_| {
+                                ErrorInternalServerError("Unable to add video to the search base")
+                            })?
+                            .wait_for_completion(&data.meillisearch_client, None, None)
+                            .await
+                            .map_err(|_| {
+                                ErrorInternalServerError("Unable to add video to the search base")
+                            })?;
+                    }
 
                     VideoUploadState::Available
                 } else {
@@ -432,7 +466,7 @@ pub mod uuid {
                 let mut video = ActiveModel::from(video);
                 let video_upload_state_string = video_upload_state.to_value().to_string();
 
-                video.set(resolution_column, video_upload_state.into());
+                video.set(resolution_column, video_upload_state.clone().into());
 
                 let (video, _) = join(
                     video.update(&data.db_connection),
@@ -446,7 +480,45 @@ pub mod uuid {
                 )
                 .await;
 
-                video.map_err(|_| ErrorInternalServerError("Unable to end the video file"))?;
+                let video =
+                    video.map_err(|_| ErrorInternalServerError("Unable to end the video file"))?;
+
+                // built from the updated row so the resolution label includes this one,
+                // the admin reindex repairs a missed insert
+                if video_upload_state == VideoUploadState::Available
+                    && !data.gorse_breaker.is_open()
+                {
+                    let inserted = data.gorse_client.insert_item(&video_to_item(&video)).await;
+
+                    data.gorse_breaker.record(inserted);
+                }
+
+                publish_upload_event(
+                    &jwt.sub,
+                    &UploadEvent::State {
+                        uuid: params.uuid,
+                        resolution: params.resolution,
+                        state: video_upload_state.to_value(),
+                        error: (video_upload_state == VideoUploadState::Unavailable)
+                            .then(|| "The file is not a valid webm video".to_string()),
+                    },
+                    &data.redis_client,
+                )
+                .await;
+
+                match video_upload_state {
+                    VideoUploadState::Available if first_available && !video.hidden => {
+                        notify_upload(&video, &data).await.ok();
+                        // a first video makes the channel worth finding in the search
+                        update_channel_index(&video.user_id, &data).await.ok();
+                    }
+                    VideoUploadState::Unavailable => {
+                        notify_upload_failed(&video, params.resolution, &data)
+                            .await
+                            .ok();
+                    }
+                    _ => {}
+                }
             }
 
             Ok(HttpResponse::Ok().finish())
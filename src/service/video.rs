@@ -29,6 +29,7 @@ use crate::{
     entity::video,
     util::{
         get_authentication_data, get_gorse_user_id,
+        trending::{record_engagement, Engagement},
         video::{find_video, VIDEO_REDIS_TIMEOUT},
         video::{get_resolution_availability, valid_resolution},
     },
@@ -109,10 +110,10 @@ pub mod uuid {
                     .await?;
 
                     let (start_timestamp, end_timestamp, buffer) = task::spawn_blocking({
-                        let uuid = params.uuid.clone();
-                        let resolution = params.resolution.clone();
-                        let start_timestamp = params.start_timestamp.clone();
-                        let end_timestamp = params.end_timestamp.clone();
+                        let uuid = params.uuid;
+                        let resolution = params.resolution;
+                        let start_timestamp = params.start_timestamp;
+                        let end_timestamp = params.end_timestamp;
 
                         move || -> Result<(u64, u64, Vec<u8>), &str> {
                             let mut buffer = Vec::new();
@@ -216,11 +217,7 @@ pub mod uuid {
 
                             file.seek(start_timestamp / timescale).unwrap();
 
-                            loop {
-                                let Ok(true) = file.next_frame(&mut frame) else {
-                                    break;
-                                };
-
+                            while let Ok(true) = file.next_frame(&mut frame) {
                                 let timestamp = frame.timestamp * timescale;
 
                                 if timestamp > end_timestamp {
@@ -251,8 +248,8 @@ pub mod uuid {
                         }
                     })
                     .await
-                    .unwrap_or_else(|_| Err("Unable to create the video stream"))
-                    .map_err(|error| ErrorInternalServerError(error))?;
+                    .unwrap_or(Err("Unable to create the video stream"))
+                    .map_err(ErrorInternalServerError)?;
 
                     let video_timestamp_key =
                         format!("video:timestamp:{}:{}", params.uuid, params.resolution);
@@ -290,10 +287,10 @@ pub mod uuid {
                         }
                     };
 
-                    {
+                    let jwt = get_authentication_data(&request, &data).await;
+
+                    if let Some(user_id) = get_gorse_user_id(&request, &jwt, &data).await {
                         // update views
-                        let jwt = get_authentication_data(&request, &data.clerk).await;
-                        let user_id = get_gorse_user_id(&request, &jwt).await;
                         let view_key = format!("view:{user_id}:{}", params.uuid);
                         let mut view_duration = params.end_timestamp - params.start_timestamp
                             + data
@@ -318,6 +315,9 @@ pub mod uuid {
                             )
                             .await;
 
+                            record_engagement(&params.uuid, Engagement::View, &data.redis_client)
+                                .await;
+
                             if let Ok(Some(video)) = video {
                                 let views = video.views + 1;
                                 let mut video = video::ActiveModel::from(video);
@@ -8,25 +8,201 @@ use actix_web::{
     web::Data,
     HttpResponse, Responder,
 };
-use actix_web_validator5::Path;
+use actix_web_validator5::{Path, Query};
 use chrono::{DateTime, Utc};
 use futures::future::join;
 use gorse_rs::Feedback;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use meilisearch_sdk::search::{MatchingStrategies, Selectors};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
 use serde::Deserialize;
-use serde_json::json;
-use tokio::fs::metadata;
+use serde_json::{json, Value};
 use validator::Validate;
 
 use crate::{
-    entity::{like, sea_orm_active_enums::VideoUploadState, video},
+    entity::{comment, like, sea_orm_active_enums::VideoUploadState, video},
     util::{
-        channel::get_channel_info, get_authentication_data, get_gorse_user_id,
-        video::get_resolutions,
+        channel::{channel_info_or_unknown, get_channel_info, get_channels_info},
+        comment::{get_comments, CommentSort},
+        get_authentication_data, get_gorse_user_id,
+        history::get_resume_position,
+        playlist::{
+            find_visible_playlist, get_liked_videos, get_playlist_videos, LIKED_LIST, LIKED_TITLE,
+        },
+        subscription::{count_subscribers, is_subscribed},
+        video::{find_public_videos, get_resolutions, get_stream_lengths, get_tags},
     },
-    AppState,
+    AppState, MeilliDocument,
 };
 
+const RELATED_VIDEOS_LIMIT: usize = 12;
+
+/// Gorse neighbours first, then videos sharing the title and tags words when there are not enough.
+async fn get_related_videos(
+    video: &video::Model,
+    user_id: Option<&str>,
+    recommendation_timestamp: &str,
+    data: &AppState<'_>,
+) -> actix_web::Result<Vec<Value>> {
+    let uuid = video.uuid.to_string();
+    let neighbors = data
+        .gorse_breaker
+        .call(data.gorse_client.get_item_neighbors(&uuid))
+        .await;
+    let mut item_ids: Vec<String> = neighbors
+        .unwrap_or_default()
+        .into_iter()
+        .map(|score| score.id)
+        .filter(|item_id| item_id != &uuid)
+        .take(RELATED_VIDEOS_LIMIT)
+        .collect();
+
+    if item_ids.len() < RELATED_VIDEOS_LIMIT {
+        let similarity_query = [video.title.clone()]
+            .into_iter()
+            .chain(get_tags(video))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let results = data
+            .video_index
+            .search()
+            .with_query(&similarity_query)
+            .with_limit(RELATED_VIDEOS_LIMIT + 1)
+            .with_attributes_to_search_on(&["title", "tags"])
+            .with_matching_strategy(MatchingStrategies::LAST)
+            .with_attributes_to_retrieve(Selectors::Some(&["id"]))
+            .execute::<MeilliDocument>()
+            .await
+            .map(|results| results.hits)
+            .unwrap_or_default();
+
+        for result in results {
+            if item_ids.len() >= RELATED_VIDEOS_LIMIT {
+                break;
+            }
+
+            if result.result.id != uuid && !item_ids.contains(&result.result.id) {
+                item_ids.push(result.result.id);
+            }
+        }
+    }
+
+    let uuids: Vec<Uuid> = item_ids
+        .iter()
+        .filter_map(|item_id| Uuid::parse_str(item_id).ok())
+        .collect();
+    let mut videos = find_public_videos()
+        .filter(video::Column::Uuid.is_in(uuids.clone()))
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find a videos"))?;
+
+    // keeps the recommendation order
+    videos.sort_by_key(|video| uuids.iter().position(|uuid| uuid == &video.uuid));
+
+    let channels_info = get_channels_info(
+        &videos.iter().map(|video| video.user_id.clone()).collect(),
+        &data.clerk,
+        &data.redis_client,
+    )
+    .await;
+    let videos: Vec<&video::Model> = videos
+        .iter()
+        .filter(|video| channels_info.contains_key(&video.user_id))
+        .collect();
+
+    // only what is actually shown counts as an impression
+    if let Some(user_id) = user_id.filter(|_| !videos.is_empty()) {
+        data.gorse_breaker
+            .call(
+                data.gorse_client.insert_feedback(
+                    &videos
+                        .iter()
+                        .map(|video| Feedback {
+                            feedback_type: "display".to_string(),
+                            user_id: user_id.to_string(),
+                            item_id: video.uuid.to_string(),
+                            timestamp: recommendation_timestamp.to_string(),
+                        })
+                        .collect::<Vec<Feedback>>(),
+                ),
+            )
+            .await;
+    }
+
+    Ok(videos
+        .iter()
+        .map(|video| {
+            json!({
+                "uuid": video.uuid,
+                "title": video.title,
+                "views": video.views,
+                "timestamp": video.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                "duration": video.duration,
+                "channel_info": channels_info[&video.user_id],
+            })
+        })
+        .collect())
+}
+
+/// The next-up panel of a playlist, only when the video is part of it.
+async fn get_watch_playlist(
+    list: &str,
+    video: &video::Model,
+    user_id: Option<&str>,
+    data: &AppState<'_>,
+) -> actix_web::Result<Option<Value>> {
+    let (id, title, videos) = if list == LIKED_LIST {
+        let Some(user_id) = user_id else {
+            return Ok(None);
+        };
+
+        (
+            LIKED_LIST.to_string(),
+            LIKED_TITLE.to_string(),
+            get_liked_videos(user_id, &data.db_connection).await?,
+        )
+    } else {
+        let Ok(id) = Uuid::parse_str(list) else {
+            return Ok(None);
+        };
+        let Ok(playlist) = find_visible_playlist(&id, user_id, &data.db_connection).await else {
+            return Ok(None);
+        };
+        let videos = get_playlist_videos(&playlist.id, &data.db_connection).await?;
+
+        (playlist.id.to_string(), playlist.title, videos)
+    };
+    let Some(position) = videos.iter().position(|item| item.uuid == video.uuid) else {
+        return Ok(None);
+    };
+    let channels_info = get_channels_info(
+        &videos.iter().map(|video| video.user_id.clone()).collect(),
+        &data.clerk,
+        &data.redis_client,
+    )
+    .await;
+
+    Ok(Some(json!({
+        "id": id,
+        "title": title,
+        "position": position + 1,
+        "count": videos.len(),
+        "next": videos.get(position + 1).map(|video| video.uuid),
+        "videos": videos
+            .iter()
+            .map(|item| {
+                json!({
+                    "uuid": item.uuid,
+                    "title": item.title,
+                    "duration": item.duration,
+                    "channel_info": channel_info_or_unknown(&channels_info, &item.user_id),
+                    "current": item.uuid == video.uuid,
+                })
+            })
+            .collect::<Vec<Value>>(),
+    })))
+}
+
 pub mod uuid {
     use super::*;
 
@@ -35,46 +211,51 @@ pub mod uuid {
         uuid: Uuid,
     }
 
+    #[derive(Deserialize, Validate, Debug)]
+    struct GetWatchQuery {
+        #[validate(length(min = 1, max = 36))]
+        list: Option<String>,
+    }
+
     #[get("/watch/{uuid}")]
     async fn get(
         request: HttpRequest,
         params: Path<GetWatch>,
+        query: Query<GetWatchQuery>,
         data: Data<AppState<'_>>,
     ) -> actix_web::Result<impl Responder> {
-        let jwt = get_authentication_data(&request, &data.clerk).await;
-        let user_id = get_gorse_user_id(&request, &jwt).await;
+        let jwt = get_authentication_data(&request, &data).await;
+        let user_id = get_gorse_user_id(&request, &jwt, &data).await;
         let recommendation_timestamp =
             (DateTime::<Utc>::from(SystemTime::now()) + Duration::new(3600, 0)).to_rfc3339();
 
+        let open_feedback = async {
+            if let Some(user_id) = &user_id {
+                data.gorse_breaker
+                    .call(data.gorse_client.insert_feedback(&vec![Feedback {
+                        feedback_type: "open".to_string(),
+                        user_id: user_id.clone(),
+                        item_id: params.uuid.to_string(),
+                        timestamp: recommendation_timestamp.clone(),
+                    }]))
+                    .await;
+            }
+        };
         let (_, db) = join(
-            data.gorse_client.insert_feedback(&vec![Feedback {
-                feedback_type: "open".to_string(),
-                user_id: user_id,
-                item_id: params.uuid.to_string(),
-                timestamp: recommendation_timestamp,
-            }]),
+            open_feedback,
             video::Entity::find_by_id(params.uuid).one(&data.db_connection),
         )
         .await;
 
         let video = db
             .map_err(|_| ErrorInternalServerError("Unable to find a video with this resolution"))?
+            .filter(|video| !video.hidden)
             .ok_or_else(|| ErrorNotFound("Unable to find a video with this resolution"))?;
         let channel_info =
             get_channel_info(&video.user_id, &data.clerk, &data.redis_client).await?;
         let resolutions =
             get_resolutions(&video, VideoUploadState::eq, VideoUploadState::Available);
-        let mut lengths = Vec::new();
-        let mut bitrates = Vec::new();
-
-        for resolution in resolutions.iter() {
-            let length = metadata(format!("./video/{}/{}.webm", resolution, params.uuid))
-                .await?
-                .len();
-
-            lengths.push(length);
-            bitrates.push(length as f64 / video.duration)
-        }
+        let (lengths, bitrates) = get_stream_lengths(&video, &resolutions).await?;
 
         const DEFAULT_META_DESCRIPTION: &str =
             "Apparemment pas de spoil par ici donc pas de description.";
@@ -89,16 +270,56 @@ pub mod uuid {
             Some((tags, tags_short)) => (Some(tags), Some(tags_short)),
             None => (None, None),
         };
-        let liked = match jwt {
+        let liked = match &jwt {
             Some(jwt) => like::Entity::find()
                 .filter(like::Column::Uuid.eq(params.uuid))
-                .filter(like::Column::UserId.eq(jwt.sub))
+                .filter(like::Column::UserId.eq(&jwt.sub))
                 .one(&data.db_connection)
                 .await
                 .map(|like| like.is_some())
                 .unwrap_or(false),
             None => false,
         };
+        let subscribers = count_subscribers(&video.user_id, &data.db_connection).await?;
+        let subscribed = match &jwt {
+            Some(jwt) => is_subscribed(&jwt.sub, &video.user_id, &data.db_connection).await,
+            None => false,
+        };
+        let comments = get_comments(
+            &video,
+            None,
+            CommentSort::default(),
+            0,
+            jwt.as_ref().map(|jwt| jwt.sub.as_str()),
+            &data,
+        )
+        .await?;
+        let playlist = match &query.list {
+            Some(list) => {
+                get_watch_playlist(
+                    list,
+                    &video,
+                    jwt.as_ref().map(|jwt| jwt.sub.as_str()),
+                    &data,
+                )
+                .await?
+            }
+            None => None,
+        };
+        let related =
+            get_related_videos(&video, user_id.as_deref(), &recommendation_timestamp, &data)
+                .await?;
+        let resume_at = match &jwt {
+            Some(jwt) => get_resume_position(&jwt.sub, &video, &data.db_connection).await,
+            None => None,
+        };
+        let comments_count = comment::Entity::find()
+            .filter(comment::Column::Uuid.eq(params.uuid))
+            .filter(comment::Column::Deleted.eq(false))
+            .filter(comment::Column::Hidden.eq(false))
+            .count(&data.db_connection)
+            .await
+            .unwrap_or_default();
 
         Ok(
             HttpResponse::Ok()
@@ -134,8 +355,17 @@ pub mod uuid {
                                 "views": video.views,
                                 "likes": video.likes,
                                 "liked": liked,
+                                "channel_user_id": channel_info.user_id,
                                 "channel_username": channel_info.username,
                                 "channel_profil_picture": channel_info.profil_picture,
+                                "channel_subscribers": subscribers,
+                                "subscribed": subscribed,
+                                "user_id": jwt.map(|jwt| jwt.sub),
+                                "comments": comments,
+                                "comments_count": comments_count,
+                                "playlist": playlist,
+                                "related": related,
+                                "resume_at": resume_at,
                             }),
                         )
                         .unwrap(),
@@ -1,21 +1,53 @@
-use actix_web::error::ErrorInternalServerError;
-use clerk_rs::{apis::users_api::User, clerk::Clerk};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use actix_web::{
+    error::{ErrorInternalServerError, ErrorNotFound},
+    http::StatusCode,
+    web::Data,
+};
+use clerk_rs::{
+    apis::{users_api::User, Error},
+    clerk::Clerk,
+};
 use fred::{
     clients::RedisClient,
-    interfaces::KeysInterface,
+    interfaces::{KeysInterface, SetsInterface},
     types::{Expiration, RedisValue},
 };
+use futures::future::join_all;
+use sea_orm::{EntityTrait, QuerySelect};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{entity::channel, util::subscription::count_subscribers, AppState, MeilliDocument};
 
 pub const CHANNEL_INFO_REDIS_TIMEOUT: i64 = 3600 * 4;
+/// Channels whose info was just fetched from Clerk, checked against their search document.
+const CHANNEL_SYNC_REDIS_KEY: &str = "channel_sync";
+const CHANNEL_SYNC_BATCH_SIZE: usize = 100;
+const CHANNEL_SYNC_INTERVAL: Duration = Duration::from_secs(60);
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ChannelInfo {
     pub user_id: String,
     pub username: String,
     pub profil_picture: String,
 }
 
+impl ChannelInfo {
+    /// Stands in for a user Clerk couldn't give, so their comments and reports stay visible.
+    pub fn unknown(user_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            username: "Utilisateur inconnu".to_string(),
+            profil_picture: "/android-chrome-192x192.png".to_string(),
+        }
+    }
+}
+
 pub async fn get_channel_info(
     user_id: &str,
     clerk: &Clerk,
@@ -30,7 +62,12 @@ pub async fn get_channel_info(
     if value == "nil" {
         let user = User::get_user(clerk, user_id)
             .await
-            .map_err(|_| ErrorInternalServerError("Unable to get channel info"))?;
+            .map_err(|error| match error {
+                Error::ResponseError(response) if response.status == StatusCode::NOT_FOUND => {
+                    ErrorNotFound("Channel not found")
+                }
+                _ => ErrorInternalServerError("Unable to get channel info"),
+            })?;
         let channel_info = ChannelInfo {
             user_id: user_id.to_string(),
             username: user.username.unwrap_or_default().unwrap_or_default(),
@@ -47,9 +84,141 @@ pub async fn get_channel_info(
             )
             .await
             .ok();
+        redis_client
+            .sadd::<RedisValue, _, _>(CHANNEL_SYNC_REDIS_KEY, user_id)
+            .await
+            .ok();
 
         Ok(channel_info)
     } else {
         Ok(serde_json::from_str::<ChannelInfo>(&value).unwrap())
     }
 }
+
+/// Channel info of every user Clerk could give, a deleted account must not break a whole page.
+pub async fn get_channels_info(
+    user_ids: &HashSet<String>,
+    clerk: &Clerk,
+    redis_client: &RedisClient,
+) -> HashMap<String, ChannelInfo> {
+    join_all(
+        user_ids
+            .iter()
+            .map(|user_id| get_channel_info(user_id, clerk, redis_client)),
+    )
+    .await
+    .into_iter()
+    .flatten()
+    .map(|channel_info| (channel_info.user_id.clone(), channel_info))
+    .collect()
+}
+
+pub fn channel_info_or_unknown(
+    channels_info: &HashMap<String, ChannelInfo>,
+    user_id: &str,
+) -> ChannelInfo {
+    channels_info
+        .get(user_id)
+        .cloned()
+        .unwrap_or_else(|| ChannelInfo::unknown(user_id))
+}
+
+pub fn channel_to_document(
+    channel_info: &ChannelInfo,
+    description: Option<String>,
+    subscribers: u64,
+) -> MeilliDocument {
+    MeilliDocument {
+        id: channel_info.user_id.clone(),
+        value: json!({
+            "username": channel_info.username,
+            "profil_picture": channel_info.profil_picture,
+            "description": description,
+            "subscribers": subscribers,
+        }),
+    }
+}
+
+pub async fn get_channel_document(
+    user_id: &str,
+    data: &AppState<'_>,
+) -> actix_web::Result<MeilliDocument> {
+    let channel_info = get_channel_info(user_id, &data.clerk, &data.redis_client).await?;
+    let description = channel::Entity::find_by_id(user_id)
+        .select_only()
+        .column(channel::Column::Description)
+        .into_tuple::<Option<String>>()
+        .one(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find the channel"))?
+        .flatten();
+    let subscribers = count_subscribers(user_id, &data.db_connection).await?;
+
+    Ok(channel_to_document(&channel_info, description, subscribers))
+}
+
+/// Refreshes the channel search document after its description or subscribers changed.
+pub async fn update_channel_index(user_id: &str, data: &AppState<'_>) -> actix_web::Result<()> {
+    let document = get_channel_document(user_id, data).await?;
+
+    // the task is awaited on its own so the refresh can also run in a spawned job
+    let task = data
+        .channel_index
+        .add_or_replace(&[document], Some("id"))
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to update the search base"))?;
+
+    task.wait_for_completion(&data.meillisearch_client, None, None)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to update the search base"))?;
+
+    Ok(())
+}
+
+/// Refreshes the search documents whose username or picture no longer match Clerk.
+async fn sync_channel_index(data: &AppState<'_>) -> anyhow::Result<()> {
+    loop {
+        let user_ids: Vec<String> = data
+            .redis_client
+            .spop(CHANNEL_SYNC_REDIS_KEY, Some(CHANNEL_SYNC_BATCH_SIZE))
+            .await?;
+
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+
+        for user_id in user_ids {
+            // only the channels already in the index are kept in sync
+            let Ok(document) = data
+                .channel_index
+                .get_document::<MeilliDocument>(&user_id)
+                .await
+            else {
+                continue;
+            };
+            let Ok(channel_info) =
+                get_channel_info(&user_id, &data.clerk, &data.redis_client).await
+            else {
+                continue;
+            };
+
+            if document.value["username"] != channel_info.username
+                || document.value["profil_picture"] != channel_info.profil_picture
+            {
+                update_channel_index(&user_id, data).await.ok();
+            }
+        }
+    }
+}
+
+pub fn spawn_channel_sync_job(data: Data<AppState<'static>>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHANNEL_SYNC_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            sync_channel_index(&data).await.ok();
+        }
+    });
+}
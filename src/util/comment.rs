@@ -0,0 +1,245 @@
+use std::collections::HashSet;
+
+use actix_web::error::{ErrorForbidden, ErrorInternalServerError, ErrorNotFound};
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    entity::{comment, comment_block, comment_like, comment_moderation, video},
+    util::channel::{channel_info_or_unknown, get_channels_info, ChannelInfo},
+    AppState,
+};
+
+pub const COMMENT_PAGE_SIZE: u64 = 20;
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentSort {
+    #[default]
+    Newest,
+    Top,
+}
+
+pub async fn find_comment(
+    id: i64,
+    db_connection: &DatabaseConnection,
+) -> actix_web::Result<comment::Model> {
+    comment::Entity::find_by_id(id)
+        .one(db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find a comment with this id"))?
+        .ok_or_else(|| ErrorNotFound("Unable to find a comment with this id"))
+}
+
+pub fn split_banned_words(banned_words: &str) -> Vec<String> {
+    banned_words
+        .split(&[',', '\n'][..])
+        .filter_map(|word| {
+            let word = word.trim();
+
+            (!str::is_empty(word)).then_some(word)
+        })
+        .map(str::to_lowercase)
+        .collect()
+}
+
+pub fn contains_banned_words(body: &str, banned_words: &[String]) -> bool {
+    let body = body.to_lowercase();
+    let words: HashSet<&str> = body
+        .split(|char: char| !char.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    banned_words.iter().any(|banned_word| {
+        // expressions are matched as a whole, single words only against whole words
+        if banned_word.contains(char::is_whitespace) {
+            body.contains(banned_word.as_str())
+        } else {
+            words.contains(banned_word.as_str())
+        }
+    })
+}
+
+/// Checks that `user_id` is allowed to comment on `video` and returns whether the
+/// comment has to be held for review by the owner of the video.
+pub async fn check_comment(
+    video: &video::Model,
+    user_id: &str,
+    body: &str,
+    db_connection: &DatabaseConnection,
+) -> actix_web::Result<bool> {
+    if video.user_id == user_id {
+        return Ok(false);
+    }
+
+    if video.comments_disabled {
+        return Err(ErrorForbidden("Comments are disabled on this video"));
+    }
+
+    let blocked = comment_block::Entity::find_by_id((video.user_id.clone(), user_id.to_string()))
+        .one(db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to check comment permissions"))?;
+
+    if blocked.is_some() {
+        return Err(ErrorForbidden(
+            "You are not allowed to comment on this channel",
+        ));
+    }
+
+    let moderation = comment_moderation::Entity::find_by_id(video.user_id.clone())
+        .one(db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to check comment permissions"))?;
+
+    Ok(moderation.is_some_and(|moderation| {
+        contains_banned_words(body, &split_banned_words(&moderation.banned_words))
+    }))
+}
+
+pub fn comment_to_json(
+    comment: &comment::Model,
+    channel_info: &ChannelInfo,
+    liked: bool,
+    pinned: bool,
+) -> Value {
+    // comments taken down by an admin are shown as deleted
+    let deleted = comment.deleted || comment.hidden;
+
+    json!({
+        "id": comment.id,
+        "parent_id": comment.parent_id,
+        "body": (!deleted).then_some(&comment.body),
+        "timestamp": comment.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+        "edited": comment.edited,
+        "deleted": deleted,
+        "held": comment.held,
+        "pinned": pinned,
+        "likes": comment.likes,
+        "replies": comment.replies,
+        "liked": liked,
+        "channel_info": channel_info,
+    })
+}
+
+pub async fn get_comments(
+    video: &video::Model,
+    parent_id: Option<i64>,
+    sort: CommentSort,
+    page: u64,
+    user_id: Option<&str>,
+    data: &AppState<'_>,
+) -> actix_web::Result<Value> {
+    let mut select = comment::Entity::find()
+        .filter(comment::Column::Uuid.eq(video.uuid))
+        .filter(
+            Condition::any()
+                .add(
+                    Condition::all()
+                        .add(comment::Column::Deleted.eq(false))
+                        .add(comment::Column::Hidden.eq(false)),
+                )
+                .add(comment::Column::Replies.gt(0)),
+        );
+
+    // held comments are only shown to their author and to the owner of the video
+    if user_id != Some(video.user_id.as_str()) {
+        let mut condition = Condition::any().add(comment::Column::Held.eq(false));
+
+        if let Some(user_id) = user_id {
+            condition = condition.add(comment::Column::UserId.eq(user_id));
+        }
+
+        select = select.filter(condition);
+    }
+
+    let mut pinned = None;
+    let select = match parent_id {
+        // replies are read as a conversation, oldest first
+        Some(parent_id) => select
+            .filter(comment::Column::ParentId.eq(parent_id))
+            .order_by_asc(comment::Column::Timestamp),
+        None => {
+            let mut select = select.filter(comment::Column::ParentId.is_null());
+
+            if let Some(pinned_comment_id) = video.pinned_comment_id {
+                if page == 0 {
+                    pinned = select
+                        .clone()
+                        .filter(comment::Column::Id.eq(pinned_comment_id))
+                        .one(&data.db_connection)
+                        .await
+                        .map_err(|_| ErrorInternalServerError("Unable to find comments"))?;
+                }
+
+                select = select.filter(comment::Column::Id.ne(pinned_comment_id));
+            }
+
+            match sort {
+                CommentSort::Newest => select.order_by_desc(comment::Column::Timestamp),
+                CommentSort::Top => select
+                    .order_by_desc(comment::Column::Likes)
+                    .order_by_desc(comment::Column::Timestamp),
+            }
+        }
+    };
+    let mut comments = select
+        .offset(page * COMMENT_PAGE_SIZE)
+        .limit(COMMENT_PAGE_SIZE + 1)
+        .all(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find comments"))?;
+    let has_more = comments.len() as u64 > COMMENT_PAGE_SIZE;
+
+    comments.truncate(COMMENT_PAGE_SIZE as usize);
+
+    if let Some(pinned) = pinned {
+        comments.insert(0, pinned);
+    }
+
+    let liked: HashSet<i64> = match user_id {
+        Some(user_id) if !comments.is_empty() => comment_like::Entity::find()
+            .filter(comment_like::Column::UserId.eq(user_id))
+            .filter(
+                comment_like::Column::CommentId.is_in(comments.iter().map(|comment| comment.id)),
+            )
+            .all(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to find comments"))?
+            .into_iter()
+            .map(|comment_like| comment_like.comment_id)
+            .collect(),
+        _ => HashSet::new(),
+    };
+    let channels_info = get_channels_info(
+        &comments
+            .iter()
+            .map(|comment| comment.user_id.clone())
+            .collect(),
+        &data.clerk,
+        &data.redis_client,
+    )
+    .await;
+    let comments: Vec<Value> = comments
+        .iter()
+        .map(|comment| {
+            comment_to_json(
+                comment,
+                &channel_info_or_unknown(&channels_info, &comment.user_id),
+                liked.contains(&comment.id),
+                video.pinned_comment_id == Some(comment.id),
+            )
+        })
+        .collect();
+
+    Ok(json!({
+        "comments": comments,
+        "page": page,
+        "has_more": has_more,
+        "disabled": video.comments_disabled,
+        "owner": user_id == Some(video.user_id.as_str()),
+    }))
+}
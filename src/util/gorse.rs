@@ -0,0 +1,198 @@
+use std::{
+    future::Future,
+    sync::atomic::{AtomicI64, AtomicU32, Ordering},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use chrono::Utc;
+use gorse_rs::Item;
+use reqwest::{Client, Method, StatusCode, Url};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+
+use crate::AnyhowResult;
+
+const ITEMS_PAGE_SIZE: usize = 1000;
+const GORSE_FAILURE_THRESHOLD: u32 = 3;
+const GORSE_COOLDOWN: i64 = 60;
+/// `gorse_rs` has no timeout of its own, a hanging Gorse would otherwise never trip the breaker.
+const GORSE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Reindex batches are large, but a hanging request must still end.
+const GORSE_API_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize)]
+struct ItemId {
+    #[serde(rename = "ItemId")]
+    item_id: String,
+}
+
+#[derive(Deserialize)]
+struct ItemsPage {
+    #[serde(rename = "Cursor")]
+    cursor: String,
+    #[serde(rename = "Items")]
+    items: Vec<ItemId>,
+}
+
+/// Gorse REST endpoints that are not exposed by `gorse_rs`.
+pub struct GorseApi {
+    entry_point: String,
+    api_key: String,
+    client: Client,
+}
+
+impl GorseApi {
+    pub fn new(entry_point: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            entry_point: entry_point.into(),
+            api_key: api_key.into(),
+            client: Client::builder()
+                .timeout(GORSE_API_TIMEOUT)
+                .build()
+                .expect("Unable to build the Gorse HTTP client"),
+        }
+    }
+
+    pub async fn delete_feedback(
+        &self,
+        feedback_type: &str,
+        user_id: &str,
+        item_id: &str,
+    ) -> anyhow::Result<()> {
+        self.request::<Value>(
+            Method::DELETE,
+            format!(
+                "{}api/feedback/{feedback_type}/{user_id}/{item_id}",
+                self.entry_point
+            ),
+            None,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn update_item(&self, item_id: &str, item_patch: &Value) -> anyhow::Result<()> {
+        self.request::<Value>(
+            Method::PATCH,
+            format!("{}api/item/{item_id}", self.entry_point),
+            Some(item_patch),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn insert_items(&self, items: &[Item]) -> anyhow::Result<()> {
+        self.request::<Value>(
+            Method::POST,
+            format!("{}api/items", self.entry_point),
+            Some(&serde_json::to_value(items)?),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Walks the whole item list, following the cursor until Gorse returns an empty one.
+    pub async fn list_item_ids(&self) -> anyhow::Result<Vec<String>> {
+        let mut item_ids = Vec::new();
+        let mut cursor = String::new();
+
+        loop {
+            let page = self
+                .request::<ItemsPage>(
+                    Method::GET,
+                    Url::parse_with_params(
+                        &format!("{}api/items", self.entry_point),
+                        [("n", ITEMS_PAGE_SIZE.to_string()), ("cursor", cursor)],
+                    )?
+                    .to_string(),
+                    None,
+                )
+                .await?;
+
+            item_ids.extend(page.items.into_iter().map(|item| item.item_id));
+
+            if page.cursor.is_empty() {
+                return Ok(item_ids);
+            }
+
+            cursor = page.cursor;
+        }
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        url: String,
+        body: Option<&Value>,
+    ) -> anyhow::Result<T> {
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("X-API-Key", self.api_key.as_str());
+
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+
+        let response = request.send().await.anyhow()?;
+
+        match response.status() {
+            StatusCode::OK => response.json::<T>().await.anyhow(),
+            status => Err(anyhow!("{status}: {}", response.text().await?)),
+        }
+    }
+}
+
+/// Skips Gorse for a cooldown once it failed a few times in a row, so pages don't wait on it.
+#[derive(Default)]
+pub struct GorseBreaker {
+    failures: AtomicU32,
+    open_until: AtomicI64,
+}
+
+impl GorseBreaker {
+    pub fn is_open(&self) -> bool {
+        Utc::now().timestamp() < self.open_until.load(Ordering::Relaxed)
+    }
+
+    pub fn success(&self) {
+        self.failures.store(0, Ordering::Relaxed);
+    }
+
+    pub fn failure(&self) {
+        if self.failures.fetch_add(1, Ordering::Relaxed) + 1 >= GORSE_FAILURE_THRESHOLD {
+            self.failures.store(0, Ordering::Relaxed);
+            self.open_until
+                .store(Utc::now().timestamp() + GORSE_COOLDOWN, Ordering::Relaxed);
+        }
+    }
+
+    /// Runs the request unless the breaker is open, a request running past the timeout is a failure.
+    pub async fn call<T, E>(&self, request: impl Future<Output = Result<T, E>>) -> Option<T> {
+        if self.is_open() {
+            return None;
+        }
+
+        match tokio::time::timeout(GORSE_TIMEOUT, request).await {
+            Ok(result) => self.record(result),
+            Err(_) => {
+                self.failure();
+                None
+            }
+        }
+    }
+
+    pub fn record<T, E>(&self, result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.success();
+                Some(value)
+            }
+            Err(_) => {
+                self.failure();
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use actix_web::error::ErrorInternalServerError;
+use chrono::Utc;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ActiveValue::Set,
+    DatabaseConnection, EntityTrait,
+};
+use uuid::Uuid;
+
+use crate::entity::{video, watch_history, watch_history_pause};
+
+/// Past this share of the duration the video counts as watched to the end.
+const COMPLETED_RATIO: f64 = 0.9;
+/// Below this position in seconds there is nothing worth resuming.
+const RESUME_MIN_POSITION: f64 = 5.0;
+
+pub async fn is_history_paused(user_id: &str, db_connection: &DatabaseConnection) -> bool {
+    watch_history_pause::Entity::find_by_id(user_id)
+        .one(db_connection)
+        .await
+        .map(|pause| pause.is_some())
+        .unwrap_or(false)
+}
+
+/// Records where the user is in the video, nothing is kept while the history is paused.
+pub async fn record_watch(
+    user_id: &str,
+    uuid: &Uuid,
+    position: f64,
+    duration: f64,
+    db_connection: &DatabaseConnection,
+) -> actix_web::Result<()> {
+    if is_history_paused(user_id, db_connection).await {
+        return Ok(());
+    }
+
+    watch_history::Entity::insert(watch_history::ActiveModel {
+        user_id: Set(user_id.to_string()),
+        uuid: Set(*uuid),
+        position: Set(position.clamp(0.0, duration)),
+        timestamp: Set(Utc::now().naive_utc()),
+        completed: Set(position >= duration * COMPLETED_RATIO),
+    })
+    .on_conflict(
+        OnConflict::columns([watch_history::Column::UserId, watch_history::Column::Uuid])
+            .update_columns([
+                watch_history::Column::Position,
+                watch_history::Column::Timestamp,
+            ])
+            .value(
+                watch_history::Column::Completed,
+                Expr::cust("watch_history.completed OR excluded.completed"),
+            )
+            .to_owned(),
+    )
+    .exec_without_returning(db_connection)
+    .await
+    .map_err(|_| ErrorInternalServerError("Unable to update the watch history"))?;
+
+    Ok(())
+}
+
+/// Where the playback should start again, unless the video was left near its end.
+pub async fn get_resume_position(
+    user_id: &str,
+    video: &video::Model,
+    db_connection: &DatabaseConnection,
+) -> Option<f64> {
+    watch_history::Entity::find_by_id((user_id.to_string(), video.uuid))
+        .one(db_connection)
+        .await
+        .ok()
+        .flatten()
+        .map(|history| history.position)
+        .filter(|position| {
+            *position >= RESUME_MIN_POSITION && *position < video.duration * COMPLETED_RATIO
+        })
+}
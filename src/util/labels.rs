@@ -0,0 +1,188 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use actix_web::web::Data;
+use gorse_rs::User;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use uuid::Uuid;
+
+use crate::{
+    entity::{like, subscription, video, watch_history},
+    util::video::get_tags,
+    AppState,
+};
+
+const LABELS_REFRESH: Duration = Duration::from_secs(6 * 3600);
+/// Only the most recent part of the history describes the current taste.
+const LABELS_HISTORY_LIMIT: u64 = 200;
+const LABELS_TAG_COUNT: usize = 10;
+const LIKE_WEIGHT: f64 = 3.;
+const VIEW_WEIGHT: f64 = 1.;
+
+/// Coarse duration bucket, shared by the item and user labels so they can match.
+pub fn length_label(duration: f64) -> String {
+    let length = match duration {
+        duration if duration < 240. => "short",
+        duration if duration < 1200. => "medium",
+        _ => "long",
+    };
+
+    format!("length:{length}")
+}
+
+/// Top tags and lengths of the liked and watched videos, and the subscribed channels.
+pub async fn get_user_labels(
+    user_id: &str,
+    db_connection: &DatabaseConnection,
+) -> anyhow::Result<Vec<String>> {
+    let mut weights: HashMap<Uuid, f64> = HashMap::new();
+
+    for uuid in watch_history::Entity::find()
+        .select_only()
+        .column(watch_history::Column::Uuid)
+        .filter(watch_history::Column::UserId.eq(user_id))
+        .order_by_desc(watch_history::Column::Timestamp)
+        .limit(LABELS_HISTORY_LIMIT)
+        .into_tuple::<Uuid>()
+        .all(db_connection)
+        .await?
+    {
+        *weights.entry(uuid).or_default() += VIEW_WEIGHT;
+    }
+
+    for uuid in like::Entity::find()
+        .select_only()
+        .column(like::Column::Uuid)
+        .filter(like::Column::UserId.eq(user_id))
+        .order_by_desc(like::Column::Timestamp)
+        .limit(LABELS_HISTORY_LIMIT)
+        .into_tuple::<Uuid>()
+        .all(db_connection)
+        .await?
+    {
+        *weights.entry(uuid).or_default() += LIKE_WEIGHT;
+    }
+
+    let videos = video::Entity::find()
+        .filter(video::Column::Uuid.is_in(weights.keys().copied()))
+        .all(db_connection)
+        .await?;
+    let mut tags: HashMap<String, f64> = HashMap::new();
+    let mut lengths: HashMap<String, f64> = HashMap::new();
+
+    for video in &videos {
+        let weight = weights[&video.uuid];
+
+        for tag in get_tags(video) {
+            *tags.entry(tag).or_default() += weight;
+        }
+
+        *lengths.entry(length_label(video.duration)).or_default() += weight;
+    }
+
+    let mut tags: Vec<(String, f64)> = tags.into_iter().collect();
+    let total_length: f64 = lengths.values().sum();
+
+    tags.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut labels: Vec<String> = tags
+        .into_iter()
+        .take(LABELS_TAG_COUNT)
+        .map(|(tag, _)| format!("tag:{tag}"))
+        .collect();
+
+    // a length is preferred when it makes up at least a third of what was watched
+    labels.extend(
+        lengths
+            .into_iter()
+            .filter(|(_, weight)| *weight * 3. >= total_length)
+            .map(|(length, _)| length),
+    );
+    labels.extend(
+        subscription::Entity::find()
+            .select_only()
+            .column(subscription::Column::ChannelId)
+            .filter(subscription::Column::UserId.eq(user_id))
+            .into_tuple::<String>()
+            .all(db_connection)
+            .await?
+            .into_iter()
+            .map(|channel_id| format!("channel:{channel_id}")),
+    );
+
+    Ok(labels)
+}
+
+pub async fn update_user_labels(user_id: &str, data: &AppState<'_>) -> anyhow::Result<()> {
+    let labels = get_user_labels(user_id, &data.db_connection).await?;
+    let inserted = data
+        .gorse_client
+        .insert_user(&User {
+            user_id: user_id.to_string(),
+            labels,
+        })
+        .await;
+
+    data.gorse_breaker
+        .record(inserted)
+        .ok_or_else(|| anyhow::anyhow!("Unable to update the recommendation base"))?;
+
+    Ok(())
+}
+
+async fn update_labels(data: &AppState<'_>) -> anyhow::Result<()> {
+    let mut user_ids: HashSet<String> = HashSet::new();
+
+    user_ids.extend(
+        like::Entity::find()
+            .select_only()
+            .column(like::Column::UserId)
+            .distinct()
+            .into_tuple::<String>()
+            .all(&data.db_connection)
+            .await?,
+    );
+    user_ids.extend(
+        watch_history::Entity::find()
+            .select_only()
+            .column(watch_history::Column::UserId)
+            .distinct()
+            .into_tuple::<String>()
+            .all(&data.db_connection)
+            .await?,
+    );
+    user_ids.extend(
+        subscription::Entity::find()
+            .select_only()
+            .column(subscription::Column::UserId)
+            .distinct()
+            .into_tuple::<String>()
+            .all(&data.db_connection)
+            .await?,
+    );
+
+    for user_id in user_ids {
+        if data.gorse_breaker.is_open() {
+            break;
+        }
+
+        update_user_labels(&user_id, data).await.ok();
+    }
+
+    Ok(())
+}
+
+/// Refreshes the Gorse labels of every user with some activity in the background.
+pub fn spawn_labels_job(data: Data<AppState<'static>>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(LABELS_REFRESH);
+
+        loop {
+            interval.tick().await;
+
+            update_labels(&data).await.ok();
+        }
+    });
+}
@@ -1,39 +1,89 @@
-use actix_web::HttpRequest;
-use clerk_rs::{
-    apis::{jwks_api::Jwks, sessions_api::Session},
-    clerk::Clerk,
-    models::session::Status,
-    validators::actix::{validate_jwt, ClerkJwt},
-};
+use actix_web::{error::ErrorInternalServerError, HttpMessage, HttpRequest};
+use clerk_rs::validators::actix::{token_kid, validate_jwt, ClerkJwt};
+use data_url::DataUrl;
+use file_format::FileFormat;
+use session::{is_session_active, ValidatedSession};
+use visitor::{get_visitor_id, merge_visitor, touch_visitor};
+
+use crate::AppState;
 
 pub mod channel;
+pub mod comment;
+pub mod gorse;
+pub mod history;
+pub mod labels;
+pub mod notification;
+pub mod playlist;
+pub mod reindex;
+pub mod report;
+pub mod search;
+pub mod session;
+pub mod subscription;
+pub mod together;
+pub mod trending;
+pub mod upload;
 pub mod video;
+pub mod visitor;
+pub mod websocket;
 
-pub async fn get_authentication_data(request: &HttpRequest, clerk: &Clerk) -> Option<ClerkJwt> {
-    let Some(access_token) = request.cookie("__session") else {
-        return None;
-    };
-    let Ok(jwks) = Jwks::get_jwks(clerk).await else {
-        return None;
-    };
+/// Validates the `__session` cookie, the signing keys and the session status are cached.
+pub async fn get_authentication_data(
+    request: &HttpRequest,
+    data: &AppState<'_>,
+) -> Option<ClerkJwt> {
+    let access_token = request.cookie("__session")?;
+    let kid = token_kid(access_token.value().to_string()).ok()?.kid?;
+    let jwks = data.jwks.get(&kid, &data.clerk).await?;
     let Ok((_, jwt)) = validate_jwt(access_token.value(), jwks) else {
         return None;
     };
 
-    Session::get_session(clerk, &jwt.sid)
-        .await
-        .ok()
-        .map(|session| (session.status == Status::Active).then_some(jwt))
-        .flatten()
+    if !is_session_active(&jwt.sid, &data.clerk, &data.redis_client).await {
+        return None;
+    }
+
+    request
+        .extensions_mut()
+        .insert(ValidatedSession(jwt.sid.clone()));
+
+    Some(jwt)
 }
 
-pub async fn get_gorse_user_id(request: &HttpRequest, jwt: &Option<ClerkJwt>) -> String {
-    jwt.as_ref().map_or(
-        request
-            .connection_info()
-            .peer_addr()
-            .unwrap_or("global")
-            .to_string(),
-        |jwt| jwt.sub.clone(),
-    )
+/// The Clerk user when logged in, merging the visitor history on the way, or the anonymous visitor.
+/// Only a request the visitor middleware didn't see has neither.
+pub async fn get_gorse_user_id(
+    request: &HttpRequest,
+    jwt: &Option<ClerkJwt>,
+    data: &AppState<'_>,
+) -> Option<String> {
+    let visitor_id = get_visitor_id(request);
+
+    match (jwt, visitor_id) {
+        (Some(jwt), Some(visitor_id)) => {
+            merge_visitor(&visitor_id, &jwt.sub, data).await;
+
+            Some(jwt.sub.clone())
+        }
+        (Some(jwt), None) => Some(jwt.sub.clone()),
+        (None, Some(visitor_id)) => {
+            touch_visitor(&visitor_id, data).await;
+
+            Some(visitor_id.gorse_user_id())
+        }
+        (None, None) => None,
+    }
+}
+
+pub fn decode_webp_data_url(data_url: &str) -> actix_web::Result<Vec<u8>> {
+    let data = DataUrl::process(data_url)
+        .map_err(|_| ErrorInternalServerError("Unable to process image data"))?
+        .decode_to_vec()
+        .map_err(|_| ErrorInternalServerError("Unable to extract image data"))?
+        .0;
+
+    if FileFormat::from_bytes(&data).media_type() != "image/webp" {
+        return Err(ErrorInternalServerError("Wrong image mime type"));
+    }
+
+    Ok(data)
 }
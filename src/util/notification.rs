@@ -0,0 +1,164 @@
+use actix_web::error::ErrorInternalServerError;
+use sea_orm::{
+    sea_query::{OnConflict, Query as SeaQuery},
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Select,
+    Set,
+};
+
+use crate::{
+    entity::{comment, notification, sea_orm_active_enums::NotificationType, subscription, video},
+    AppState,
+};
+
+pub const NOTIFICATION_PAGE_SIZE: u64 = 20;
+pub const NOTIFICATION_BATCH_SIZE: u64 = 1000;
+pub const LIKE_MILESTONES: [i64; 8] = [10, 50, 100, 500, 1000, 10000, 100000, 1000000];
+
+/// Notifications of a user, leaving out the ones about a hidden video.
+pub fn find_notifications(user_id: &str) -> Select<notification::Entity> {
+    notification::Entity::find()
+        .filter(notification::Column::UserId.eq(user_id))
+        .filter(
+            notification::Column::Uuid.not_in_subquery(
+                SeaQuery::select()
+                    .column(video::Column::Uuid)
+                    .from(video::Entity)
+                    .and_where(video::Column::Hidden.eq(true))
+                    .to_owned(),
+            ),
+        )
+}
+
+/// Inserts the notifications, the ones already sent (same user, type, video, comment and value) are skipped.
+pub async fn notify(
+    notifications: Vec<notification::ActiveModel>,
+    db_connection: &DatabaseConnection,
+) -> actix_web::Result<()> {
+    if notifications.is_empty() {
+        return Ok(());
+    }
+
+    notification::Entity::insert_many(notifications)
+        .on_conflict(
+            OnConflict::columns([
+                notification::Column::UserId,
+                notification::Column::NotificationType,
+                notification::Column::Uuid,
+                notification::Column::CommentId,
+                notification::Column::Value,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to add notifications"))?;
+
+    Ok(())
+}
+
+/// Notifies every subscriber of the channel, subscribers are fetched and notified by batch.
+pub async fn notify_upload(video: &video::Model, data: &AppState<'_>) -> actix_web::Result<()> {
+    let mut last_user_id = String::new();
+
+    loop {
+        let user_ids: Vec<String> = subscription::Entity::find()
+            .select_only()
+            .column(subscription::Column::UserId)
+            .filter(subscription::Column::ChannelId.eq(&video.user_id))
+            .filter(subscription::Column::UserId.gt(last_user_id))
+            .order_by_asc(subscription::Column::UserId)
+            .limit(NOTIFICATION_BATCH_SIZE)
+            .into_tuple()
+            .all(&data.db_connection)
+            .await
+            .map_err(|_| ErrorInternalServerError("Unable to find subscribers"))?;
+        let Some(user_id) = user_ids.last() else {
+            return Ok(());
+        };
+        let is_last_batch = (user_ids.len() as u64) < NOTIFICATION_BATCH_SIZE;
+
+        last_user_id = user_id.clone();
+        notify(
+            user_ids
+                .into_iter()
+                .map(|user_id| notification::ActiveModel {
+                    user_id: Set(user_id),
+                    notification_type: Set(NotificationType::Upload),
+                    actor_id: Set(Some(video.user_id.clone())),
+                    uuid: Set(video.uuid),
+                    ..Default::default()
+                })
+                .collect(),
+            &data.db_connection,
+        )
+        .await?;
+
+        if is_last_batch {
+            return Ok(());
+        }
+    }
+}
+
+pub async fn notify_upload_failed(
+    video: &video::Model,
+    resolution: u16,
+    data: &AppState<'_>,
+) -> actix_web::Result<()> {
+    notify(
+        vec![notification::ActiveModel {
+            user_id: Set(video.user_id.clone()),
+            notification_type: Set(NotificationType::UploadFailed),
+            uuid: Set(video.uuid),
+            value: Set(Some(resolution as i64)),
+            ..Default::default()
+        }],
+        &data.db_connection,
+    )
+    .await
+}
+
+pub async fn notify_reply(
+    parent: &comment::Model,
+    reply: &comment::Model,
+    data: &AppState<'_>,
+) -> actix_web::Result<()> {
+    if parent.user_id == reply.user_id || reply.held {
+        return Ok(());
+    }
+
+    notify(
+        vec![notification::ActiveModel {
+            user_id: Set(parent.user_id.clone()),
+            notification_type: Set(NotificationType::Reply),
+            actor_id: Set(Some(reply.user_id.clone())),
+            uuid: Set(reply.uuid),
+            comment_id: Set(Some(reply.id)),
+            ..Default::default()
+        }],
+        &data.db_connection,
+    )
+    .await
+}
+
+/// Notifies the owner of the milestones crossed since `previous_likes`, each one is only notified once.
+pub async fn notify_like_milestone(
+    video: &video::Model,
+    previous_likes: i64,
+    data: &AppState<'_>,
+) -> actix_web::Result<()> {
+    // concurrent likes can move the count by more than one, so a milestone is never matched exactly
+    let notifications = LIKE_MILESTONES
+        .into_iter()
+        .filter(|milestone| (previous_likes + 1..=video.likes).contains(milestone))
+        .map(|milestone| notification::ActiveModel {
+            user_id: Set(video.user_id.clone()),
+            notification_type: Set(NotificationType::LikeMilestone),
+            uuid: Set(video.uuid),
+            value: Set(Some(milestone)),
+            ..Default::default()
+        })
+        .collect();
+
+    notify(notifications, &data.db_connection).await
+}
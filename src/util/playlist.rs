@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use actix_web::error::{ErrorInternalServerError, ErrorNotFound};
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    entity::{like, playlist, playlist_item, sea_orm_active_enums::PlaylistVisibility, video},
+    util::video::find_public_videos,
+    AppState, MeilliDocument,
+};
+
+pub const PLAYLIST_MAX_SIZE: u64 = 500;
+pub const WATCH_LATER_TITLE: &str = "À regarder plus tard";
+/// The liked videos list has no playlist row, it is read from the likes.
+pub const LIKED_LIST: &str = "liked";
+pub const LIKED_TITLE: &str = "Vidéos J'aime";
+
+pub async fn find_playlist(
+    id: &Uuid,
+    db_connection: &DatabaseConnection,
+) -> actix_web::Result<playlist::Model> {
+    playlist::Entity::find_by_id(*id)
+        .one(db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find a playlist with this id"))?
+        .ok_or_else(|| ErrorNotFound("Unable to find a playlist with this id"))
+}
+
+/// Private playlists are only visible to their owner, unlisted ones to anyone with the link.
+pub async fn find_visible_playlist(
+    id: &Uuid,
+    user_id: Option<&str>,
+    db_connection: &DatabaseConnection,
+) -> actix_web::Result<playlist::Model> {
+    let playlist = find_playlist(id, db_connection).await?;
+
+    if playlist.visibility == PlaylistVisibility::Private
+        && user_id != Some(playlist.user_id.as_str())
+    {
+        return Err(ErrorNotFound("Unable to find a playlist with this id"));
+    }
+
+    Ok(playlist)
+}
+
+pub async fn count_playlist_items(
+    id: &Uuid,
+    db_connection: &impl ConnectionTrait,
+) -> actix_web::Result<u64> {
+    playlist_item::Entity::find()
+        .filter(playlist_item::Column::PlaylistId.eq(*id))
+        .count(db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to count the playlist videos"))
+}
+
+/// The Watch Later list is a private playlist of its own, created on first use.
+pub async fn find_watch_later(
+    user_id: &str,
+    db_connection: &DatabaseConnection,
+) -> actix_web::Result<playlist::Model> {
+    let find = || {
+        playlist::Entity::find()
+            .filter(playlist::Column::UserId.eq(user_id))
+            .filter(playlist::Column::WatchLater.eq(true))
+            .one(db_connection)
+    };
+
+    if let Some(playlist) = find()
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find the Watch Later playlist"))?
+    {
+        return Ok(playlist);
+    }
+
+    playlist::Entity::insert(playlist::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id.to_string()),
+        title: Set(WATCH_LATER_TITLE.to_string()),
+        visibility: Set(PlaylistVisibility::Private),
+        watch_later: Set(true),
+        ..Default::default()
+    })
+    .on_conflict(OnConflict::new().do_nothing().to_owned())
+    .exec_without_returning(db_connection)
+    .await
+    .map_err(|_| ErrorInternalServerError("Unable to create the Watch Later playlist"))?;
+
+    find()
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find the Watch Later playlist"))?
+        .ok_or_else(|| ErrorInternalServerError("Unable to find the Watch Later playlist"))
+}
+
+/// Public videos in the given order, the ones that can't be watched anymore are left out.
+async fn find_ordered_videos(
+    uuids: Vec<Uuid>,
+    db_connection: &DatabaseConnection,
+) -> actix_web::Result<Vec<video::Model>> {
+    let mut videos: HashMap<Uuid, video::Model> = find_public_videos()
+        .filter(video::Column::Uuid.is_in(uuids.clone()))
+        .all(db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find the playlist videos"))?
+        .into_iter()
+        .map(|video| (video.uuid, video))
+        .collect();
+
+    Ok(uuids
+        .iter()
+        .filter_map(|uuid| videos.remove(uuid))
+        .collect())
+}
+
+pub async fn get_playlist_videos(
+    id: &Uuid,
+    db_connection: &DatabaseConnection,
+) -> actix_web::Result<Vec<video::Model>> {
+    let uuids = playlist_item::Entity::find()
+        .select_only()
+        .column(playlist_item::Column::Uuid)
+        .filter(playlist_item::Column::PlaylistId.eq(*id))
+        .order_by_asc(playlist_item::Column::Position)
+        .order_by_asc(playlist_item::Column::Timestamp)
+        .into_tuple()
+        .all(db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find the playlist videos"))?;
+
+    find_ordered_videos(uuids, db_connection).await
+}
+
+/// Most recently liked first, capped like any other playlist.
+pub async fn get_liked_videos(
+    user_id: &str,
+    db_connection: &DatabaseConnection,
+) -> actix_web::Result<Vec<video::Model>> {
+    let uuids = like::Entity::find()
+        .select_only()
+        .column(like::Column::Uuid)
+        .filter(like::Column::UserId.eq(user_id))
+        .order_by_desc(like::Column::Timestamp)
+        .limit(PLAYLIST_MAX_SIZE)
+        .into_tuple()
+        .all(db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to find the liked videos"))?;
+
+    find_ordered_videos(uuids, db_connection).await
+}
+
+pub fn playlist_to_document(playlist: &playlist::Model, video_count: u64) -> MeilliDocument {
+    MeilliDocument {
+        id: playlist.id.to_string(),
+        value: json!({
+            "title": playlist.title,
+            "description": playlist.description,
+            "video_count": video_count,
+            "timestamp": playlist.timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+            "user_id": playlist.user_id,
+        }),
+    }
+}
+
+/// Only public playlists are searchable, the others are removed from the index.
+pub async fn update_playlist_index(
+    playlist: &playlist::Model,
+    data: &AppState<'_>,
+) -> actix_web::Result<()> {
+    let task = match playlist.visibility {
+        PlaylistVisibility::Public => {
+            let video_count = count_playlist_items(&playlist.id, &data.db_connection).await?;
+
+            data.playlist_index
+                .add_documents(&[playlist_to_document(playlist, video_count)], Some("id"))
+                .await
+        }
+        _ => {
+            data.playlist_index
+                .delete_document(playlist.id.to_string())
+                .await
+        }
+    };
+
+    task.map_err(|_| ErrorInternalServerError("Unable to update the search base"))?
+        .wait_for_completion(&data.meillisearch_client, None, None)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to update the search base"))?;
+
+    Ok(())
+}
@@ -0,0 +1,329 @@
+use std::{collections::HashSet, time::Duration};
+
+use actix_web::{http::StatusCode, web::Data};
+use anyhow::anyhow;
+use fred::{
+    interfaces::KeysInterface,
+    types::{Expiration, RedisValue},
+};
+use meilisearch_sdk::{documents::DocumentsQuery, indexes::Index, task_info::TaskInfo};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entity::{channel, video},
+    util::{
+        channel::get_channel_document,
+        report::log_action,
+        video::{available_condition, video_to_document, video_to_item},
+    },
+    AppState, MeilliDocument,
+};
+
+const REINDEX_BATCH_SIZE: u64 = 500;
+/// Held while a reindex runs, it expires on its own if the process dies half-way.
+pub const REINDEX_LOCK_KEY: &str = "reindex_running";
+pub const REINDEX_LOCK_TIMEOUT: i64 = 3600;
+/// Status of the last reindex, read by the admin page while it runs.
+const REINDEX_STATUS_KEY: &str = "reindex_status";
+const REINDEX_STATUS_TIMEOUT: i64 = 86400 * 7;
+const REINDEX_TASK_INTERVAL: Duration = Duration::from_millis(500);
+/// A batch of documents can take much longer than the SDK's default five seconds.
+const REINDEX_TASK_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Deserialize)]
+struct DocumentId {
+    id: String,
+}
+
+/// What a reindex changed in the external stores.
+#[derive(Serialize, Debug, Default)]
+pub struct ReindexReport {
+    pub videos: usize,
+    pub search_added: Vec<String>,
+    pub search_updated: usize,
+    pub search_removed: Vec<String>,
+    pub recommendation_added: Vec<String>,
+    pub recommendation_updated: usize,
+    pub recommendation_removed: Vec<String>,
+    pub channels: usize,
+    pub channel_added: Vec<String>,
+    pub channel_updated: usize,
+    pub channel_removed: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum ReindexStatus {
+    Running,
+    Done { report: ReindexReport, logged: bool },
+    Failed { error: String, logged: bool },
+}
+
+async fn set_reindex_status(status: &ReindexStatus, data: &AppState<'_>) {
+    let Ok(status) = serde_json::to_string(status) else {
+        return;
+    };
+
+    data.redis_client
+        .set::<RedisValue, _, _>(
+            REINDEX_STATUS_KEY,
+            status,
+            Some(Expiration::EX(REINDEX_STATUS_TIMEOUT)),
+            None,
+            false,
+        )
+        .await
+        .ok();
+}
+
+/// The status of the running or last reindex, as JSON.
+pub async fn get_reindex_status(data: &AppState<'_>) -> Option<String> {
+    data.redis_client
+        .get::<Option<String>, _>(REINDEX_STATUS_KEY)
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Waits for a Meilisearch task, a failed one is an error rather than a change to report.
+async fn wait_for_task(task: TaskInfo, data: &AppState<'_>) -> anyhow::Result<()> {
+    let task = task
+        .wait_for_completion(
+            &data.meillisearch_client,
+            Some(REINDEX_TASK_INTERVAL),
+            Some(REINDEX_TASK_TIMEOUT),
+        )
+        .await?;
+
+    if task.is_failure() {
+        return Err(anyhow!(
+            "Unable to update the search base : {}",
+            task.unwrap_failure()
+        ));
+    }
+
+    Ok(())
+}
+
+async fn get_search_ids(index: &Index) -> anyhow::Result<HashSet<String>> {
+    let mut ids = HashSet::new();
+    let mut offset = 0;
+
+    loop {
+        let documents = DocumentsQuery::new(index)
+            .with_fields(["id"])
+            .with_offset(offset)
+            .with_limit(REINDEX_BATCH_SIZE as usize)
+            .execute::<DocumentId>()
+            .await?;
+        let count = documents.results.len();
+
+        ids.extend(documents.results.into_iter().map(|document| document.id));
+        offset += count;
+
+        if count < REINDEX_BATCH_SIZE as usize {
+            return Ok(ids);
+        }
+    }
+}
+
+/// Indexes the channels with a visible video or a customized page, a channel Clerk no longer knows is dropped.
+async fn reindex_channels(data: &AppState<'_>, report: &mut ReindexReport) -> anyhow::Result<()> {
+    let search_ids = get_search_ids(&data.channel_index).await?;
+    let mut user_ids: HashSet<String> = video::Entity::find()
+        .select_only()
+        .column(video::Column::UserId)
+        .distinct()
+        .filter(video::Column::Hidden.eq(false))
+        .filter(available_condition())
+        .into_tuple::<String>()
+        .all(&data.db_connection)
+        .await?
+        .into_iter()
+        .collect();
+
+    user_ids.extend(
+        channel::Entity::find()
+            .select_only()
+            .column(channel::Column::UserId)
+            .into_tuple::<String>()
+            .all(&data.db_connection)
+            .await?,
+    );
+
+    let mut documents = Vec::new();
+
+    for user_id in &user_ids {
+        let document = match get_channel_document(user_id, data).await {
+            Ok(document) => document,
+            Err(error) if error.as_response_error().status_code() == StatusCode::NOT_FOUND => {
+                continue;
+            }
+            // Clerk being unreachable must not empty the channel index
+            Err(error) => return Err(anyhow::anyhow!("{error}")),
+        };
+
+        match search_ids.contains(&document.id) {
+            true => report.channel_updated += 1,
+            false => report.channel_added.push(document.id.clone()),
+        }
+
+        documents.push(document);
+    }
+
+    let indexed_ids: HashSet<&String> = documents.iter().map(|document| &document.id).collect();
+
+    report.channels = documents.len();
+    report.channel_removed = search_ids
+        .into_iter()
+        .filter(|id| !indexed_ids.contains(id))
+        .collect();
+
+    for documents in documents.chunks(REINDEX_BATCH_SIZE as usize) {
+        wait_for_task(
+            data.channel_index
+                .add_or_replace(documents, Some("id"))
+                .await?,
+            data,
+        )
+        .await?;
+    }
+
+    if !report.channel_removed.is_empty() {
+        wait_for_task(
+            data.channel_index
+                .delete_documents(&report.channel_removed)
+                .await?,
+            data,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Rebuilds the search indexes and the recommendation items from every video with an available resolution,
+/// then removes what no longer matches a video or a channel.
+async fn reindex_videos(data: &AppState<'_>) -> anyhow::Result<ReindexReport> {
+    let search_ids = get_search_ids(&data.video_index).await?;
+    let recommendation_ids: HashSet<String> =
+        data.gorse_api.list_item_ids().await?.into_iter().collect();
+    let mut report = ReindexReport::default();
+    let mut indexed_ids = HashSet::new();
+    let mut video_ids = HashSet::new();
+    let mut pages = video::Entity::find()
+        .filter(available_condition())
+        .order_by_asc(video::Column::Uuid)
+        .paginate(&data.db_connection, REINDEX_BATCH_SIZE);
+
+    while let Some(videos) = pages.fetch_and_next().await? {
+        // hidden videos stay in gorse as hidden items, but are kept out of the search
+        let documents: Vec<MeilliDocument> = videos
+            .iter()
+            .filter(|video| !video.hidden)
+            .map(video_to_document)
+            .collect();
+        let items: Vec<_> = videos.iter().map(video_to_item).collect();
+
+        if !documents.is_empty() {
+            wait_for_task(
+                data.video_index
+                    .add_or_replace(&documents, Some("id"))
+                    .await?,
+                data,
+            )
+            .await?;
+        }
+
+        data.gorse_api.insert_items(&items).await?;
+
+        for document in documents {
+            match search_ids.contains(&document.id) {
+                true => report.search_updated += 1,
+                false => report.search_added.push(document.id.clone()),
+            }
+
+            indexed_ids.insert(document.id);
+        }
+
+        for item in items {
+            match recommendation_ids.contains(&item.item_id) {
+                true => report.recommendation_updated += 1,
+                false => report.recommendation_added.push(item.item_id.clone()),
+            }
+
+            video_ids.insert(item.item_id);
+        }
+
+        report.videos += videos.len();
+    }
+
+    report.search_removed = search_ids
+        .into_iter()
+        .filter(|id| !indexed_ids.contains(id))
+        .collect();
+    report.recommendation_removed = recommendation_ids
+        .into_iter()
+        .filter(|id| !video_ids.contains(id))
+        .collect();
+
+    if !report.search_removed.is_empty() {
+        wait_for_task(
+            data.video_index
+                .delete_documents(&report.search_removed)
+                .await?,
+            data,
+        )
+        .await?;
+    }
+
+    for item_id in &report.recommendation_removed {
+        data.gorse_client
+            .delete_item(item_id)
+            .await
+            .map_err(|error| anyhow::anyhow!("{error}"))?;
+    }
+
+    reindex_channels(data, &mut report).await?;
+
+    Ok(report)
+}
+
+/// Runs the reindex in the background, its outcome is kept for the admin page and written to the audit log.
+pub async fn start_reindex(user_id: String, data: Data<AppState<'static>>) {
+    set_reindex_status(&ReindexStatus::Running, &data).await;
+
+    tokio::spawn(async move {
+        let result = reindex_videos(&data).await;
+        let details = match &result {
+            Ok(report) => format!(
+                "{} vidéos, recherche +{} -{}, recommandation +{} -{}, chaines +{} -{}",
+                report.videos,
+                report.search_added.len(),
+                report.search_removed.len(),
+                report.recommendation_added.len(),
+                report.recommendation_removed.len(),
+                report.channel_added.len(),
+                report.channel_removed.len(),
+            ),
+            Err(error) => format!("échec : {error}"),
+        };
+        let logged = log_action(&user_id, "reindex", None, Some(details), &data)
+            .await
+            .is_ok();
+        let status = match result {
+            Ok(report) => ReindexStatus::Done { report, logged },
+            Err(error) => ReindexStatus::Failed {
+                error: error.to_string(),
+                logged,
+            },
+        };
+
+        set_reindex_status(&status, &data).await;
+        data.redis_client
+            .del::<RedisValue, _>(REINDEX_LOCK_KEY)
+            .await
+            .ok();
+    });
+}
@@ -0,0 +1,140 @@
+use actix_web::error::ErrorInternalServerError;
+use clerk_rs::validators::actix::ClerkJwt;
+use fred::{interfaces::KeysInterface, types::RedisValue};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use serde_json::json;
+
+use crate::{
+    entity::{
+        audit_log, comment, report,
+        sea_orm_active_enums::{ReportTargetType, VideoUploadState},
+        video,
+    },
+    util::video::{get_resolutions, video_to_document},
+    AppState,
+};
+
+const AUDIT_LOG_DETAILS_MAX_LENGTH: usize = 1000;
+
+pub fn is_admin(jwt: &ClerkJwt, data: &AppState<'_>) -> bool {
+    data.admin_user_ids.contains(&jwt.sub)
+}
+
+/// Records an admin action, the target is left out for actions on a whole store.
+pub async fn log_action(
+    user_id: &str,
+    action: &str,
+    target: Option<(ReportTargetType, &str)>,
+    details: Option<String>,
+    data: &AppState<'_>,
+) -> actix_web::Result<()> {
+    let (target_type, target_id) = target.unzip();
+
+    audit_log::ActiveModel {
+        user_id: Set(user_id.to_string()),
+        action: Set(action.to_string()),
+        target_type: Set(target_type),
+        target_id: Set(target_id.map(str::to_string)),
+        details: Set(
+            details.map(|details| details.chars().take(AUDIT_LOG_DETAILS_MAX_LENGTH).collect())
+        ),
+        ..Default::default()
+    }
+    .insert(&data.db_connection)
+    .await
+    .map_err(|_| ErrorInternalServerError("Unable to write the audit log"))?;
+
+    Ok(())
+}
+
+/// Marks every open report on the target as resolved and returns how many were.
+pub async fn resolve_reports(
+    target_type: ReportTargetType,
+    target_id: &str,
+    data: &AppState<'_>,
+) -> actix_web::Result<u64> {
+    report::Entity::update_many()
+        .col_expr(report::Column::Resolved, true.into())
+        .filter(report::Column::TargetType.eq(target_type))
+        .filter(report::Column::TargetId.eq(target_id))
+        .filter(report::Column::Resolved.eq(false))
+        .exec(&data.db_connection)
+        .await
+        .map(|result| result.rows_affected)
+        .map_err(|_| ErrorInternalServerError("Unable to resolve reports"))
+}
+
+/// Hides or restores a video, nothing is deleted so a takedown can always be undone.
+pub async fn set_video_hidden(
+    video: video::Model,
+    hidden: bool,
+    data: &AppState<'_>,
+) -> actix_web::Result<video::Model> {
+    let mut video = video::ActiveModel::from(video);
+
+    video.hidden = Set(hidden);
+
+    video
+        .update(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to update the video"))
+}
+
+/// Brings the search and recommendation bases in line with the hidden flag, safe to run again.
+pub async fn sync_video_hidden(video: &video::Model, data: &AppState<'_>) -> actix_web::Result<()> {
+    let hidden = video.hidden;
+    let resolutions = get_resolutions(video, VideoUploadState::ne, VideoUploadState::Unavailable);
+
+    data.redis_client
+        .del::<RedisValue, _>(
+            resolutions
+                .iter()
+                .map(|resolution| format!("video:{}:{resolution}", video.uuid))
+                .collect::<Vec<String>>(),
+        )
+        .await
+        .ok();
+
+    // only videos with an available resolution are indexed
+    if get_resolutions(video, VideoUploadState::eq, VideoUploadState::Available).is_empty() {
+        return Ok(());
+    }
+
+    let task = match hidden {
+        true => {
+            data.video_index
+                .delete_document(video.uuid.to_string())
+                .await
+        }
+        false => {
+            data.video_index
+                .add_documents(&[video_to_document(video)], Some("id"))
+                .await
+        }
+    };
+
+    task.map_err(|_| ErrorInternalServerError("Unable to update the search base"))?
+        .wait_for_completion(&data.meillisearch_client, None, None)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to update the search base"))?;
+    data.gorse_api
+        .update_item(&video.uuid.to_string(), &json!({ "IsHidden": hidden }))
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to update the recommendation base"))?;
+
+    Ok(())
+}
+
+pub async fn set_comment_hidden(
+    comment: comment::Model,
+    hidden: bool,
+    data: &AppState<'_>,
+) -> actix_web::Result<comment::Model> {
+    let mut comment = comment::ActiveModel::from(comment);
+
+    comment.hidden = Set(hidden);
+    comment
+        .update(&data.db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to update the comment"))
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use meilisearch_sdk::{client::Client, settings::Settings, tasks::Task};
+use serde::{Deserialize, Serialize};
+
+/// Bump it whenever the settings below change, so they are applied again at the next startup.
+const SEARCH_SETTINGS_VERSION: u32 = 1;
+/// Keeps the settings version applied to each index.
+const SEARCH_META_INDEX: &str = "meta";
+
+const VIDEO_FILTERABLE_ATTRIBUTES: [&str; 4] = ["timestamp_unix", "duration", "tags", "user_id"];
+const VIDEO_SORTABLE_ATTRIBUTES: [&str; 3] = ["timestamp_unix", "views", "likes"];
+
+#[derive(Serialize, Deserialize, Debug)]
+struct IndexMeta {
+    id: String,
+    settings_version: u32,
+}
+
+fn synonyms() -> HashMap<&'static str, Vec<&'static str>> {
+    HashMap::from([
+        ("tuto", vec!["tutoriel", "tutorial"]),
+        ("tutoriel", vec!["tuto", "tutorial"]),
+        ("jeu", vec!["jeux", "gaming"]),
+        ("musique", vec!["music", "son"]),
+        ("film", vec!["movie", "cinéma"]),
+    ])
+}
+
+fn video_settings() -> Settings {
+    Settings::new()
+        .with_searchable_attributes(["title", "tags", "description"])
+        .with_filterable_attributes(VIDEO_FILTERABLE_ATTRIBUTES)
+        .with_sortable_attributes(VIDEO_SORTABLE_ATTRIBUTES)
+        .with_displayed_attributes([
+            "id",
+            "title",
+            "description",
+            "tags",
+            "views",
+            "likes",
+            "duration",
+            "timestamp",
+            "timestamp_unix",
+            "user_id",
+        ])
+        .with_ranking_rules([
+            "words",
+            "typo",
+            "proximity",
+            "attribute",
+            "sort",
+            "exactness",
+            "views:desc",
+        ])
+        .with_synonyms(synonyms())
+}
+
+fn playlist_settings() -> Settings {
+    Settings::new()
+        .with_searchable_attributes(["title", "description"])
+        .with_filterable_attributes(["user_id"])
+        .with_sortable_attributes(["video_count"])
+        .with_displayed_attributes([
+            "id",
+            "title",
+            "description",
+            "video_count",
+            "timestamp",
+            "user_id",
+        ])
+        .with_synonyms(synonyms())
+}
+
+fn channel_settings() -> Settings {
+    Settings::new()
+        .with_searchable_attributes(["username", "description"])
+        .with_sortable_attributes(["subscribers"])
+        .with_displayed_attributes([
+            "id",
+            "username",
+            "profil_picture",
+            "description",
+            "subscribers",
+        ])
+        .with_ranking_rules([
+            "words",
+            "typo",
+            "proximity",
+            "attribute",
+            "sort",
+            "exactness",
+            "subscribers:desc",
+        ])
+}
+
+fn check_task(task: Task, uid: &str) -> anyhow::Result<()> {
+    if task.is_failure() {
+        return Err(anyhow!(
+            "Unable to set up the {uid} search index : {}",
+            task.unwrap_failure()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Creates the index when it's missing, then applies its settings unless they are already up to date.
+async fn bootstrap_index(client: &Client, uid: &str, settings: &Settings) -> anyhow::Result<()> {
+    if client.get_index(uid).await.is_err() {
+        check_task(
+            client
+                .create_index(uid, Some("id"))
+                .await?
+                .wait_for_completion(client, None, None)
+                .await?,
+            uid,
+        )?;
+    }
+
+    let meta_index = client.index(SEARCH_META_INDEX);
+
+    if let Ok(meta) = meta_index.get_document::<IndexMeta>(uid).await {
+        if meta.settings_version == SEARCH_SETTINGS_VERSION {
+            return Ok(());
+        }
+    }
+
+    check_task(
+        client
+            .index(uid)
+            .set_settings(settings)
+            .await?
+            .wait_for_completion(client, None, None)
+            .await?,
+        uid,
+    )?;
+    check_task(
+        meta_index
+            .add_or_replace(
+                &[IndexMeta {
+                    id: uid.to_string(),
+                    settings_version: SEARCH_SETTINGS_VERSION,
+                }],
+                Some("id"),
+            )
+            .await?
+            .wait_for_completion(client, None, None)
+            .await?,
+        SEARCH_META_INDEX,
+    )?;
+
+    Ok(())
+}
+
+pub async fn bootstrap_search_indexes(client: &Client) -> anyhow::Result<()> {
+    bootstrap_index(client, "video", &video_settings()).await?;
+    bootstrap_index(client, "playlist", &playlist_settings()).await?;
+    bootstrap_index(client, "channel", &channel_settings()).await
+}
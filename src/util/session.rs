@@ -0,0 +1,210 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    cookie::{Cookie, CookieJar, Key, SameSite},
+    dev::{Service, ServiceRequest, ServiceResponse},
+    web::Data,
+    HttpMessage, HttpRequest,
+};
+use clerk_rs::{
+    apis::{
+        jwks_api::{Jwks, JwksKey, JwksModel},
+        sessions_api::Session,
+    },
+    clerk::Clerk,
+    models::session::Status,
+};
+use fred::{
+    prelude::{KeysInterface, RedisClient},
+    types::{Expiration, RedisValue},
+};
+use tokio::sync::Mutex;
+
+use crate::AppState;
+
+const SESSION_REDIS_TIMEOUT: i64 = 60;
+/// Last session validated in this browser, signed so only its owner can end it.
+const SESSION_OWNER_COOKIE: &str = "session_owner";
+/// Tokens with a made up `kid` must not turn into one JWKS request each.
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Clerk signing keys by `kid`, fetched again only when a token uses an unknown one.
+#[derive(Default)]
+pub struct JwksCache {
+    keys: RwLock<HashMap<String, JwksKey>>,
+    refreshed_at: Mutex<Option<Instant>>,
+}
+
+impl JwksCache {
+    /// A JWKS holding only the key of `kid`, as expected by `validate_jwt`.
+    pub async fn get(&self, kid: &str, clerk: &Clerk) -> Option<JwksModel> {
+        if let Some(jwks) = self.find(kid) {
+            return Some(jwks);
+        }
+
+        let mut refreshed_at = self.refreshed_at.lock().await;
+
+        // another request may have refreshed the keys while this one was waiting
+        if let Some(jwks) = self.find(kid) {
+            return Some(jwks);
+        }
+
+        if refreshed_at.is_some_and(|refreshed_at| refreshed_at.elapsed() < JWKS_REFRESH_INTERVAL) {
+            return None;
+        }
+
+        *refreshed_at = Some(Instant::now());
+
+        let jwks = Jwks::get_jwks(clerk).await.ok()?;
+
+        // clerk-rs can only validate RS256 keys and panics on anything else
+        *self.keys.write().unwrap() = jwks
+            .keys
+            .into_iter()
+            .filter(|key| key.alg == "RS256")
+            .map(|key| (key.kid.clone(), key))
+            .collect();
+
+        self.find(kid)
+    }
+
+    fn find(&self, kid: &str) -> Option<JwksModel> {
+        self.keys.read().unwrap().get(kid).map(|key| JwksModel {
+            keys: vec![JwksKey {
+                use_key: key.use_key.clone(),
+                kty: key.kty.clone(),
+                kid: key.kid.clone(),
+                alg: key.alg.clone(),
+                n: key.n.clone(),
+                e: key.e.clone(),
+            }],
+        })
+    }
+}
+
+fn session_key(session_id: &str) -> String {
+    format!("session:{session_id}")
+}
+
+/// Whether the Clerk session is still active, remembered for a short while in both cases.
+pub async fn is_session_active(
+    session_id: &str,
+    clerk: &Clerk,
+    redis_client: &RedisClient,
+) -> bool {
+    let key = session_key(session_id);
+
+    if let Ok(Some(active)) = redis_client.get::<Option<i64>, _>(&key).await {
+        return active == 1;
+    }
+
+    let Ok(session) = Session::get_session(clerk, session_id).await else {
+        return false;
+    };
+    let active = session.status == Status::Active;
+
+    redis_client
+        .set::<RedisValue, _, _>(
+            &key,
+            active as i64,
+            Some(Expiration::EX(SESSION_REDIS_TIMEOUT)),
+            None,
+            false,
+        )
+        .await
+        .ok();
+
+    active
+}
+
+/// Drops the cached status so the next request asks Clerk again.
+pub async fn invalidate_session(session_id: &str, redis_client: &RedisClient) {
+    redis_client
+        .del::<RedisValue, _>(session_key(session_id))
+        .await
+        .ok();
+}
+
+/// Inserted in the request once its Clerk session is validated.
+#[derive(Clone, Debug)]
+pub struct ValidatedSession(pub String);
+
+/// The session id this browser last authenticated with.
+pub fn read_session_owner_cookie(request: &HttpRequest, key: &Key) -> Option<String> {
+    let mut jar = CookieJar::new();
+
+    jar.add_original(request.cookie(SESSION_OWNER_COOKIE)?);
+
+    Some(
+        jar.signed(key)
+            .get(SESSION_OWNER_COOKIE)?
+            .value()
+            .to_string(),
+    )
+}
+
+fn session_owner_cookie(session_id: &str, key: &Key) -> Option<Cookie<'static>> {
+    let mut jar = CookieJar::new();
+
+    jar.signed_mut(key).add(
+        Cookie::build(SESSION_OWNER_COOKIE, session_id.to_string())
+            .path("/")
+            .http_only(true)
+            .secure(true)
+            .same_site(SameSite::Strict)
+            .finish(),
+    );
+
+    jar.delta().next().cloned()
+}
+
+pub fn session_owner_removal_cookie() -> Cookie<'static> {
+    let mut cookie = Cookie::build(SESSION_OWNER_COOKIE, "").path("/").finish();
+
+    cookie.make_removal();
+
+    cookie
+}
+
+/// Remembers the validated session in a signed cookie, the session endpoint checks it.
+pub fn session_middleware<S, B>(
+    request: ServiceRequest,
+    service: &S,
+) -> impl Future<Output = actix_web::Result<ServiceResponse<B>>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    let key = request
+        .app_data::<Data<AppState<'static>>>()
+        .map(|data| data.visitor_key.clone());
+    let response = service.call(request);
+
+    async move {
+        let mut response = response.await?;
+        let Some(key) = key else {
+            return Ok(response);
+        };
+        let session = response
+            .request()
+            .extensions()
+            .get::<ValidatedSession>()
+            .cloned();
+
+        if let Some(ValidatedSession(session_id)) = session {
+            let owner = read_session_owner_cookie(response.request(), &key);
+
+            if owner.as_deref() != Some(session_id.as_str()) {
+                if let Some(cookie) = session_owner_cookie(&session_id, &key) {
+                    response.response_mut().add_cookie(&cookie).ok();
+                }
+            }
+        }
+
+        Ok(response)
+    }
+}
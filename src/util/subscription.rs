@@ -0,0 +1,27 @@
+use actix_web::error::ErrorInternalServerError;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter};
+
+use crate::entity::subscription;
+
+pub async fn count_subscribers(
+    channel_id: &str,
+    db_connection: &DatabaseConnection,
+) -> actix_web::Result<u64> {
+    subscription::Entity::find()
+        .filter(subscription::Column::ChannelId.eq(channel_id))
+        .count(db_connection)
+        .await
+        .map_err(|_| ErrorInternalServerError("Unable to count subscribers"))
+}
+
+pub async fn is_subscribed(
+    user_id: &str,
+    channel_id: &str,
+    db_connection: &DatabaseConnection,
+) -> bool {
+    subscription::Entity::find_by_id((user_id.to_string(), channel_id.to_string()))
+        .one(db_connection)
+        .await
+        .map(|subscription| subscription.is_some())
+        .unwrap_or(false)
+}
@@ -0,0 +1,640 @@
+use std::collections::{HashMap, HashSet};
+
+use actix_web::HttpRequest;
+use chrono::Utc;
+use fred::{
+    clients::{RedisClient, SubscriberClient},
+    interfaces::{
+        EventInterface, HashesInterface, KeysInterface, ListInterface, PubsubInterface,
+        SetsInterface,
+    },
+    types::{Expiration, RedisValue, SetOptions},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast::{error::RecvError, Sender};
+use uuid::Uuid;
+
+use crate::{
+    util::{channel::get_channel_info, get_authentication_data, visitor::get_visitor_id},
+    AppState,
+};
+
+pub const TOGETHER_EVENT_CHANNEL: &str = "together_event";
+/// A room nobody touched for a day is forgotten.
+const TOGETHER_ROOM_TIMEOUT: i64 = 86400;
+/// A member that stopped refreshing its presence is gone, even if its instance couldn't say so.
+const TOGETHER_MEMBER_TIMEOUT: i64 = 30;
+/// Messages kept for the members joining later.
+const TOGETHER_CHAT_BACKLOG: i64 = 50;
+pub const TOGETHER_CHAT_MAX_LENGTH: usize = 500;
+pub const TOGETHER_REACTIONS: [&str; 7] = ["👍", "❤️", "😂", "😮", "😢", "👏", "🔥"];
+
+/// Authoritative playback state of a room.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Room {
+    pub video: Uuid,
+    /// Identity of the member in control of the playback.
+    pub host: String,
+    pub position: f64,
+    pub paused: bool,
+    pub rate: f64,
+    /// Server time in milliseconds at which the position was taken.
+    pub updated_at: i64,
+}
+
+impl Room {
+    /// Position extrapolated to a server time in milliseconds.
+    pub fn position_at(&self, time: i64) -> f64 {
+        match self.paused {
+            true => self.position,
+            false => self.position + (time - self.updated_at) as f64 / 1000. * self.rate,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Member {
+    /// One id per connection.
+    pub id: Uuid,
+    /// Clerk user id or visitor id, stable across reconnects.
+    pub identity: String,
+    pub username: String,
+    pub profil_picture: String,
+    /// Only members with a Clerk session may open peer connections, as they expose their address.
+    #[serde(default)]
+    pub authenticated: bool,
+    #[serde(default)]
+    pub voice: bool,
+    pub joined_at: i64,
+    pub seen_at: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MemberInfo {
+    pub id: Uuid,
+    pub username: String,
+    pub profil_picture: String,
+    pub host: bool,
+    pub authenticated: bool,
+    pub voice: bool,
+    pub muted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChatMessage {
+    pub member_id: Uuid,
+    pub username: String,
+    pub profil_picture: String,
+    pub text: String,
+    /// Server time in milliseconds.
+    pub timestamp: i64,
+}
+
+/// Messages sent to the room members.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TogetherEvent {
+    Welcome {
+        member_id: Uuid,
+    },
+    Pong {
+        client_time: f64,
+        server_time: i64,
+    },
+    State {
+        video: Uuid,
+        position: f64,
+        paused: bool,
+        rate: f64,
+        updated_at: i64,
+    },
+    Members {
+        members: Vec<MemberInfo>,
+    },
+    /// WebRTC session description or ICE candidate relayed from another member.
+    Signal {
+        from: Uuid,
+        signal: Value,
+    },
+    Chat(ChatMessage),
+    /// Backlog sent to a member when it joins.
+    History {
+        messages: Vec<ChatMessage>,
+    },
+    Reaction {
+        member_id: Uuid,
+        username: String,
+        emoji: String,
+    },
+    /// The command was ignored, `reason` is `muted` or `rate_limited`.
+    Refused {
+        reason: &'static str,
+    },
+    Kicked,
+}
+
+impl From<&Room> for TogetherEvent {
+    fn from(room: &Room) -> Self {
+        TogetherEvent::State {
+            video: room.video,
+            position: room.position,
+            paused: room.paused,
+            rate: room.rate,
+            updated_at: room.updated_at,
+        }
+    }
+}
+
+/// Messages sent by a room member.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TogetherCommand {
+    Ping { client_time: f64 },
+    Play { position: f64 },
+    Pause { position: f64 },
+    Seek { position: f64 },
+    Rate { rate: f64 },
+    Voice { enabled: bool },
+    Signal { to: Uuid, signal: Value },
+    Chat { text: String },
+    Reaction { emoji: String },
+    Mute { member_id: Uuid, muted: bool },
+    Kick { member_id: Uuid },
+}
+
+fn room_key(room_id: &Uuid) -> String {
+    format!("together:{room_id}")
+}
+
+fn members_key(room_id: &Uuid) -> String {
+    format!("together:{room_id}:members")
+}
+
+/// Identities the host muted, kept apart from the room so a stale copy of it can't undo a mute.
+fn muted_key(room_id: &Uuid) -> String {
+    format!("together:{room_id}:muted")
+}
+
+/// Identities the host kicked, they can't join again.
+fn banned_key(room_id: &Uuid) -> String {
+    format!("together:{room_id}:banned")
+}
+
+fn chat_key(room_id: &Uuid) -> String {
+    format!("together:{room_id}:chat")
+}
+
+pub fn now_millis() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+/// Builds the member of a new connection, logged in users show up with their channel.
+pub async fn get_member(request: &HttpRequest, data: &AppState<'_>) -> Option<Member> {
+    let now = Utc::now().timestamp();
+    let (identity, username, profil_picture, authenticated) =
+        match get_authentication_data(request, data).await {
+            Some(jwt) => {
+                let channel_info = get_channel_info(&jwt.sub, &data.clerk, &data.redis_client)
+                    .await
+                    .ok()?;
+
+                (
+                    jwt.sub,
+                    channel_info.username,
+                    channel_info.profil_picture,
+                    true,
+                )
+            }
+            None => (
+                get_visitor_id(request)?.gorse_user_id(),
+                "Invité".to_string(),
+                String::new(),
+                false,
+            ),
+        };
+
+    Some(Member {
+        id: Uuid::new_v4(),
+        identity,
+        username,
+        profil_picture,
+        authenticated,
+        voice: false,
+        joined_at: now,
+        seen_at: now,
+    })
+}
+
+pub async fn get_room(room_id: &Uuid, redis_client: &RedisClient) -> Option<Room> {
+    redis_client
+        .get::<Option<String>, _>(room_key(room_id))
+        .await
+        .ok()
+        .flatten()
+        .and_then(|room| serde_json::from_str(&room).ok())
+}
+
+pub async fn set_room(
+    room_id: &Uuid,
+    room: &Room,
+    redis_client: &RedisClient,
+) -> anyhow::Result<()> {
+    redis_client
+        .set::<RedisValue, _, _>(
+            room_key(room_id),
+            serde_json::to_string(room)?,
+            Some(Expiration::EX(TOGETHER_ROOM_TIMEOUT)),
+            None,
+            false,
+        )
+        .await?;
+    redis_client
+        .expire::<RedisValue, _>(members_key(room_id), TOGETHER_ROOM_TIMEOUT)
+        .await?;
+    for key in [chat_key(room_id), muted_key(room_id), banned_key(room_id)] {
+        redis_client
+            .expire::<RedisValue, _>(key, TOGETHER_ROOM_TIMEOUT)
+            .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn create_room(
+    video: Uuid,
+    host: String,
+    position: f64,
+    redis_client: &RedisClient,
+) -> anyhow::Result<Uuid> {
+    let room_id = Uuid::new_v4();
+
+    set_room(
+        &room_id,
+        &Room {
+            video,
+            host,
+            position,
+            paused: true,
+            rate: 1.,
+            updated_at: now_millis(),
+        },
+        redis_client,
+    )
+    .await?;
+
+    Ok(room_id)
+}
+
+/// Publishes the event on the room channel, so it reaches the members connected to every app instance.
+pub async fn publish_together_event(
+    room_id: &Uuid,
+    event: &TogetherEvent,
+    redis_client: &RedisClient,
+) {
+    let Ok(event) = serde_json::to_string(event) else {
+        return;
+    };
+
+    redis_client
+        .publish::<RedisValue, _, _>(format!("{TOGETHER_EVENT_CHANNEL}:{room_id}"), event)
+        .await
+        .ok();
+}
+
+/// Publishes the event for a single member of the room, wherever it is connected.
+pub async fn publish_member_event(
+    room_id: &Uuid,
+    member_id: &Uuid,
+    event: &TogetherEvent,
+    redis_client: &RedisClient,
+) {
+    let Ok(event) = serde_json::to_string(event) else {
+        return;
+    };
+
+    redis_client
+        .publish::<RedisValue, _, _>(
+            format!("{TOGETHER_EVENT_CHANNEL}:{room_id}:{member_id}"),
+            event,
+        )
+        .await
+        .ok();
+}
+
+/// Forwards the room events published on Redis to the local connections, as `(room_id, member_id, event)`,
+/// without member for the events sent to the whole room.
+pub async fn forward_together_events(
+    subscriber_client: SubscriberClient,
+    sender: Sender<(Uuid, Option<Uuid>, String)>,
+) -> anyhow::Result<()> {
+    let mut message_rx = subscriber_client.message_rx();
+
+    subscriber_client
+        .psubscribe(format!("{TOGETHER_EVENT_CHANNEL}:*"))
+        .await?;
+    tokio::spawn(async move {
+        loop {
+            let message = match message_rx.recv().await {
+                Ok(message) => message,
+                // the skipped messages are lost but the following ones must still be forwarded
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            let Some(target) = message
+                .channel
+                .strip_prefix(&format!("{TOGETHER_EVENT_CHANNEL}:"))
+            else {
+                continue;
+            };
+            let (room_id, member_id) = match target.split_once(':') {
+                Some((room_id, member_id)) => (room_id, Some(member_id)),
+                None => (target, None),
+            };
+            let Ok(room_id) = Uuid::parse_str(room_id) else {
+                continue;
+            };
+            let Ok(member_id) = member_id.map(Uuid::parse_str).transpose() else {
+                continue;
+            };
+            let Some(event) = message.value.as_string() else {
+                continue;
+            };
+
+            // no local connection is listening
+            sender.send((room_id, member_id, event)).ok();
+        }
+    });
+
+    Ok(())
+}
+
+pub async fn touch_member(
+    room_id: &Uuid,
+    member: &mut Member,
+    redis_client: &RedisClient,
+) -> anyhow::Result<()> {
+    member.seen_at = Utc::now().timestamp();
+
+    redis_client
+        .hset::<RedisValue, _, _>(
+            members_key(room_id),
+            (member.id.to_string(), serde_json::to_string(member)?),
+        )
+        .await?;
+
+    Ok(())
+}
+
+async fn get_all_members(
+    room_id: &Uuid,
+    redis_client: &RedisClient,
+) -> anyhow::Result<Vec<Member>> {
+    Ok(redis_client
+        .hgetall::<HashMap<String, String>, _>(members_key(room_id))
+        .await?
+        .into_values()
+        .filter_map(|member| serde_json::from_str(&member).ok())
+        .collect())
+}
+
+/// Connected members, oldest first.
+pub async fn get_members(
+    room_id: &Uuid,
+    redis_client: &RedisClient,
+) -> anyhow::Result<Vec<Member>> {
+    let min_seen_at = Utc::now().timestamp() - TOGETHER_MEMBER_TIMEOUT;
+    let mut members: Vec<Member> = get_all_members(room_id, redis_client)
+        .await?
+        .into_iter()
+        .filter(|member| member.seen_at >= min_seen_at)
+        .collect();
+
+    members.sort_by_key(|member| member.joined_at);
+
+    Ok(members)
+}
+
+/// Returns true when some members were removed.
+pub async fn remove_stale_members(
+    room_id: &Uuid,
+    redis_client: &RedisClient,
+) -> anyhow::Result<bool> {
+    let min_seen_at = Utc::now().timestamp() - TOGETHER_MEMBER_TIMEOUT;
+    let stale_ids: Vec<String> = get_all_members(room_id, redis_client)
+        .await?
+        .into_iter()
+        .filter(|member| member.seen_at < min_seen_at)
+        .map(|member| member.id.to_string())
+        .collect();
+
+    if stale_ids.is_empty() {
+        return Ok(false);
+    }
+
+    redis_client
+        .hdel::<RedisValue, _, _>(members_key(room_id), stale_ids)
+        .await?;
+
+    Ok(true)
+}
+
+/// Hands the room to the oldest member when the host left, then sends the member list to everyone.
+pub async fn update_members(room_id: &Uuid, redis_client: &RedisClient) -> anyhow::Result<()> {
+    let Some(mut room) = get_room(room_id, redis_client).await else {
+        return Ok(());
+    };
+    let members = get_members(room_id, redis_client).await?;
+    let muted: HashSet<String> = redis_client.smembers(muted_key(room_id)).await?;
+
+    if let Some(oldest) = members.first() {
+        if !members.iter().any(|member| member.identity == room.host) {
+            room.host = oldest.identity.clone();
+
+            set_room(room_id, &room, redis_client).await?;
+        }
+    }
+
+    publish_together_event(
+        room_id,
+        &TogetherEvent::Members {
+            members: members
+                .into_iter()
+                .map(|member| MemberInfo {
+                    id: member.id,
+                    host: member.identity == room.host,
+                    username: member.username,
+                    profil_picture: member.profil_picture,
+                    authenticated: member.authenticated,
+                    voice: member.voice,
+                    muted: muted.contains(&member.identity),
+                })
+                .collect(),
+        },
+        redis_client,
+    )
+    .await;
+
+    Ok(())
+}
+
+pub async fn leave_room(
+    room_id: &Uuid,
+    member_id: &Uuid,
+    redis_client: &RedisClient,
+) -> anyhow::Result<()> {
+    redis_client
+        .hdel::<RedisValue, _, _>(members_key(room_id), member_id.to_string())
+        .await?;
+
+    update_members(room_id, redis_client).await
+}
+
+/// Removes every connection of an identity from the member list.
+pub async fn remove_identity(
+    room_id: &Uuid,
+    identity: &str,
+    redis_client: &RedisClient,
+) -> anyhow::Result<Vec<Uuid>> {
+    let member_ids: Vec<Uuid> = get_all_members(room_id, redis_client)
+        .await?
+        .into_iter()
+        .filter(|member| member.identity == identity)
+        .map(|member| member.id)
+        .collect();
+
+    if !member_ids.is_empty() {
+        redis_client
+            .hdel::<RedisValue, _, _>(
+                members_key(room_id),
+                member_ids
+                    .iter()
+                    .map(Uuid::to_string)
+                    .collect::<Vec<String>>(),
+            )
+            .await?;
+    }
+
+    Ok(member_ids)
+}
+
+pub async fn is_muted(room_id: &Uuid, identity: &str, redis_client: &RedisClient) -> bool {
+    redis_client
+        .sismember::<bool, _, _>(muted_key(room_id), identity)
+        .await
+        .unwrap_or(false)
+}
+
+pub async fn set_muted(
+    room_id: &Uuid,
+    identity: &str,
+    muted: bool,
+    redis_client: &RedisClient,
+) -> anyhow::Result<()> {
+    let key = muted_key(room_id);
+
+    match muted {
+        true => {
+            redis_client
+                .sadd::<RedisValue, _, _>(&key, identity)
+                .await?
+        }
+        false => {
+            redis_client
+                .srem::<RedisValue, _, _>(&key, identity)
+                .await?
+        }
+    };
+    redis_client
+        .expire::<RedisValue, _>(&key, TOGETHER_ROOM_TIMEOUT)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn is_banned(room_id: &Uuid, identity: &str, redis_client: &RedisClient) -> bool {
+    redis_client
+        .sismember::<bool, _, _>(banned_key(room_id), identity)
+        .await
+        .unwrap_or(false)
+}
+
+pub async fn ban_identity(
+    room_id: &Uuid,
+    identity: &str,
+    redis_client: &RedisClient,
+) -> anyhow::Result<()> {
+    let key = banned_key(room_id);
+
+    redis_client
+        .sadd::<RedisValue, _, _>(&key, identity)
+        .await?;
+    redis_client
+        .expire::<RedisValue, _>(&key, TOGETHER_ROOM_TIMEOUT)
+        .await?;
+
+    Ok(())
+}
+
+/// Counts the messages of an identity over a fixed window, returns false past the limit.
+pub async fn allow_message(
+    room_id: &Uuid,
+    identity: &str,
+    kind: &str,
+    limit: i64,
+    window: i64,
+    redis_client: &RedisClient,
+) -> bool {
+    let key = format!("together:{room_id}:{kind}:{identity}");
+
+    // the counter is created with its expiry so it can't be left without one
+    if redis_client
+        .set::<RedisValue, _, _>(
+            &key,
+            0,
+            Some(Expiration::EX(window)),
+            Some(SetOptions::NX),
+            false,
+        )
+        .await
+        .is_err()
+    {
+        return false;
+    }
+
+    let Ok(count) = redis_client.incr::<i64, _>(&key).await else {
+        return false;
+    };
+
+    count <= limit
+}
+
+pub async fn push_chat_message(
+    room_id: &Uuid,
+    message: &ChatMessage,
+    redis_client: &RedisClient,
+) -> anyhow::Result<()> {
+    let key = chat_key(room_id);
+
+    redis_client
+        .rpush::<RedisValue, _, _>(&key, serde_json::to_string(message)?)
+        .await?;
+    redis_client
+        .ltrim::<RedisValue, _>(&key, -TOGETHER_CHAT_BACKLOG, -1)
+        .await?;
+    redis_client
+        .expire::<RedisValue, _>(&key, TOGETHER_ROOM_TIMEOUT)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_chat_messages(room_id: &Uuid, redis_client: &RedisClient) -> Vec<ChatMessage> {
+    redis_client
+        .lrange::<Vec<String>, _>(chat_key(room_id), 0, -1)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|message| serde_json::from_str(&message).ok())
+        .collect()
+}
@@ -0,0 +1,153 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use actix_web::web::Data;
+use chrono::Utc;
+use fred::{
+    clients::RedisClient,
+    interfaces::{KeysInterface, SortedSetsInterface},
+    types::{Expiration, RedisValue},
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, QueryFilter, QuerySelect};
+use uuid::Uuid;
+
+use crate::{entity::video, util::video::find_public_videos, AnyhowResult, AppState};
+
+const TRENDING_REDIS_KEY: &str = "trending";
+/// One sorted set of engagement per hour, scored by video.
+const TRENDING_EVENTS_KEY: &str = "trending:events";
+const TRENDING_WINDOW_HOURS: i64 = 72;
+const TRENDING_HALF_LIFE_HOURS: f64 = 24.;
+const TRENDING_REFRESH: Duration = Duration::from_secs(600);
+pub const TRENDING_SIZE: usize = 200;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Engagement {
+    View,
+    Like,
+    Unlike,
+    Share,
+}
+
+impl Engagement {
+    fn weight(self) -> f64 {
+        match self {
+            Engagement::View => 1.,
+            Engagement::Like => 5.,
+            Engagement::Unlike => -5.,
+            Engagement::Share => 10.,
+        }
+    }
+}
+
+pub async fn record_engagement(uuid: &Uuid, engagement: Engagement, redis_client: &RedisClient) {
+    let key = format!("{TRENDING_EVENTS_KEY}:{}", Utc::now().timestamp() / 3600);
+
+    redis_client
+        .zincrby::<RedisValue, _, _>(&key, engagement.weight(), uuid.to_string())
+        .await
+        .ok();
+    redis_client
+        .expire::<RedisValue, _>(&key, (TRENDING_WINDOW_HOURS + 1) * 3600)
+        .await
+        .ok();
+}
+
+/// Sums the hourly engagement of the window, each hour halving its weight every half-life.
+async fn compute_trending(
+    redis_client: &RedisClient,
+    db_connection: &DatabaseConnection,
+) -> anyhow::Result<Vec<Uuid>> {
+    let hour = Utc::now().timestamp() / 3600;
+    let mut scores: HashMap<Uuid, f64> = HashMap::new();
+
+    for age in 0..TRENDING_WINDOW_HOURS {
+        let events = redis_client
+            .zrange::<HashMap<String, f64>, _, _, _>(
+                format!("{TRENDING_EVENTS_KEY}:{}", hour - age),
+                0,
+                -1,
+                None,
+                false,
+                None,
+                true,
+            )
+            .await?;
+        let decay = 0.5_f64.powf(age as f64 / TRENDING_HALF_LIFE_HOURS);
+
+        for (uuid, score) in events {
+            if let Ok(uuid) = Uuid::parse_str(&uuid) {
+                *scores.entry(uuid).or_default() += score * decay;
+            }
+        }
+    }
+
+    let mut scores: Vec<(Uuid, f64)> = scores
+        .into_iter()
+        .filter(|(_, score)| *score > 0.)
+        .collect();
+
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    // hidden, deleted or unavailable videos are dropped
+    let public_uuids: HashSet<Uuid> = find_public_videos()
+        .select_only()
+        .column(video::Column::Uuid)
+        .filter(video::Column::Uuid.is_in(scores.iter().map(|(uuid, _)| *uuid)))
+        .into_tuple::<Uuid>()
+        .all(db_connection)
+        .await?
+        .into_iter()
+        .collect();
+
+    Ok(scores
+        .into_iter()
+        .map(|(uuid, _)| uuid)
+        .filter(|uuid| public_uuids.contains(uuid))
+        .take(TRENDING_SIZE)
+        .collect())
+}
+
+/// The last computed trending list, empty until the first computation.
+pub async fn get_trending(redis_client: &RedisClient) -> Vec<Uuid> {
+    redis_client
+        .get::<Option<String>, _>(TRENDING_REDIS_KEY)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|trending| serde_json::from_str(&trending).ok())
+        .unwrap_or_default()
+}
+
+async fn update_trending(data: &AppState<'_>) -> anyhow::Result<()> {
+    let trending = compute_trending(&data.redis_client, &data.db_connection).await?;
+
+    data.redis_client
+        .set::<RedisValue, _, _>(
+            TRENDING_REDIS_KEY,
+            serde_json::to_string(&trending)?,
+            // outlives a few refreshes, so a stopped job doesn't leave a stale list forever
+            Some(Expiration::EX(TRENDING_REFRESH.as_secs() as i64 * 6)),
+            None,
+            false,
+        )
+        .await
+        .anyhow()?;
+
+    Ok(())
+}
+
+/// Recomputes the trending list in the background every few minutes.
+pub fn spawn_trending_job(data: Data<AppState<'static>>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TRENDING_REFRESH);
+
+        loop {
+            interval.tick().await;
+
+            update_trending(&data).await.ok();
+        }
+    });
+}
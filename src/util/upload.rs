@@ -0,0 +1,75 @@
+use fred::{
+    clients::{RedisClient, SubscriberClient},
+    interfaces::{EventInterface, PubsubInterface},
+    types::RedisValue,
+};
+use serde::Serialize;
+use tokio::sync::broadcast::{error::RecvError, Sender};
+use uuid::Uuid;
+
+pub const UPLOAD_EVENT_CHANNEL: &str = "upload_event";
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UploadEvent {
+    Progress {
+        uuid: Uuid,
+        resolution: u16,
+        bytes: u64,
+    },
+    State {
+        uuid: Uuid,
+        resolution: u16,
+        state: String,
+        error: Option<String>,
+    },
+}
+
+/// Publishes the event on the uploader channel, so it reaches every app instance.
+pub async fn publish_upload_event(user_id: &str, event: &UploadEvent, redis_client: &RedisClient) {
+    let Ok(event) = serde_json::to_string(event) else {
+        return;
+    };
+
+    redis_client
+        .publish::<RedisValue, _, _>(format!("{UPLOAD_EVENT_CHANNEL}:{user_id}"), event)
+        .await
+        .ok();
+}
+
+/// Forwards the upload events published on Redis to the local event streams, as `(user_id, event)`.
+pub async fn forward_upload_events(
+    subscriber_client: SubscriberClient,
+    sender: Sender<(String, String)>,
+) -> anyhow::Result<()> {
+    let mut message_rx = subscriber_client.message_rx();
+
+    subscriber_client
+        .psubscribe(format!("{UPLOAD_EVENT_CHANNEL}:*"))
+        .await?;
+    tokio::spawn(async move {
+        loop {
+            let message = match message_rx.recv().await {
+                Ok(message) => message,
+                // the skipped messages are lost but the following ones must still be forwarded
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            let Some(user_id) = message
+                .channel
+                .strip_prefix(&format!("{UPLOAD_EVENT_CHANNEL}:"))
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            let Some(event) = message.value.as_string() else {
+                continue;
+            };
+
+            // no local stream is listening
+            sender.send((user_id, event)).ok();
+        }
+    });
+
+    Ok(())
+}
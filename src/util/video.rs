@@ -1,18 +1,29 @@
 use std::collections::HashSet;
 
 use actix_web::error::{ErrorInternalServerError, ErrorNotFound};
+use chrono::{Duration, Utc};
 use fred::{
     clients::RedisClient,
     interfaces::KeysInterface,
     types::{Expiration, RedisValue},
 };
-use sea_orm::{ActiveEnum, DatabaseConnection, EntityTrait};
+use gorse_rs::Item;
+use sea_orm::{
+    sea_query::Expr, ActiveEnum, ColumnTrait, Condition, DatabaseConnection, EntityTrait, Order,
+    QueryFilter, QueryOrder, Select,
+};
+use serde_json::json;
+use tokio::fs::metadata;
 use uuid::Uuid;
 use validator::ValidationError;
 
-use crate::entity::{
-    sea_orm_active_enums::VideoUploadState,
-    video::{self, Model},
+use crate::{
+    entity::{
+        sea_orm_active_enums::VideoUploadState,
+        video::{self, Model},
+    },
+    util::labels::length_label,
+    MeilliDocument,
 };
 
 pub const VIDEO_REDIS_TIMEOUT: i64 = 3600;
@@ -35,14 +46,14 @@ pub async fn get_resolution_availability(
             .await
             .ok();
 
-        if availability != VideoUploadState::Available.to_value().to_string() {
+        if availability != VideoUploadState::Available.to_value() {
             return Err(ErrorNotFound("Unable to find a video with this resolution"));
         }
 
         Ok(())
     } else {
         find_video_by_resolution(
-            &uuid,
+            uuid,
             resolution_to_column(resolution)?,
             VideoUploadState::Available,
             db_connection,
@@ -58,13 +69,70 @@ pub async fn find_video(
     uuid: &Uuid,
     db_connection: &DatabaseConnection,
 ) -> actix_web::Result<video::Model> {
-    video::Entity::find_by_id(uuid.clone())
+    video::Entity::find_by_id(*uuid)
         .one(db_connection)
         .await
         .map_err(|_| ErrorInternalServerError("Unable to find a video with this resolution"))?
         .ok_or_else(|| ErrorNotFound("Unable to find a video with this resolution"))
 }
 
+pub async fn find_visible_video(
+    uuid: &Uuid,
+    db_connection: &DatabaseConnection,
+) -> actix_web::Result<video::Model> {
+    let video = find_video(uuid, db_connection).await?;
+
+    if video.hidden {
+        return Err(ErrorNotFound("Unable to find a video with this resolution"));
+    }
+
+    Ok(video)
+}
+
+pub fn find_channel_videos(user_id: &str) -> Select<video::Entity> {
+    video::Entity::find().filter(video::Column::UserId.eq(user_id))
+}
+
+/// Videos that anyone can watch.
+pub fn find_public_videos() -> Select<video::Entity> {
+    video::Entity::find()
+        .filter(video::Column::Hidden.eq(false))
+        .filter(available_condition())
+}
+
+/// Public videos of the last days, ranked by views with likes weighing more.
+pub fn find_trending_videos(days: i64) -> Select<video::Entity> {
+    find_public_videos()
+        .filter(video::Column::Timestamp.gte(Utc::now().naive_utc() - Duration::days(days)))
+        .order_by(Expr::cust("views + likes * 10"), Order::Desc)
+        .order_by_desc(video::Column::Timestamp)
+}
+
+pub fn find_latest_videos() -> Select<video::Entity> {
+    find_public_videos().order_by_desc(video::Column::Timestamp)
+}
+
+pub fn find_public_channel_videos(user_id: &str) -> Select<video::Entity> {
+    find_public_videos().filter(video::Column::UserId.eq(user_id))
+}
+
+/// Matches videos with at least one available resolution.
+pub fn available_condition() -> Condition {
+    [
+        video::Column::State144p,
+        video::Column::State240p,
+        video::Column::State360p,
+        video::Column::State480p,
+        video::Column::State720p,
+        video::Column::State1080p,
+        video::Column::State1440p,
+    ]
+    .into_iter()
+    .fold(Condition::any(), |condition, column| {
+        condition.add(column.eq(VideoUploadState::Available))
+    })
+}
+
 pub async fn find_video_by_resolution(
     uuid: &Uuid,
     resolution_column: video::Column,
@@ -87,7 +155,10 @@ pub async fn find_video_by_resolution(
     redis_client
         .set::<RedisValue, _, _>(
             format!("video:{uuid}:{resolution}"),
-            resolution_video_upload_state.to_value().to_string(),
+            match video.hidden {
+                true => "hidden".to_string(),
+                false => resolution_video_upload_state.to_value().to_string(),
+            },
             Some(Expiration::EX(VIDEO_REDIS_TIMEOUT)),
             None,
             false,
@@ -95,7 +166,7 @@ pub async fn find_video_by_resolution(
         .await
         .ok();
 
-    if resolution_video_upload_state != &video_upload_state {
+    if video.hidden || resolution_video_upload_state != &video_upload_state {
         return Err(ErrorNotFound("Unable to find a video with this resolution"));
     }
 
@@ -134,6 +205,81 @@ pub fn get_resolutions<T: Fn(&VideoUploadState, &VideoUploadState) -> bool>(
     resolutions
 }
 
+/// File size of each resolution, with the matching bitrate.
+pub async fn get_stream_lengths(
+    video: &Model,
+    resolutions: &[i32],
+) -> std::io::Result<(Vec<u64>, Vec<f64>)> {
+    let mut lengths = Vec::new();
+    let mut bitrates = Vec::new();
+
+    for resolution in resolutions {
+        let length = metadata(format!("./video/{}/{}.webm", resolution, video.uuid))
+            .await?
+            .len();
+
+        lengths.push(length);
+        bitrates.push(length as f64 / video.duration)
+    }
+
+    Ok((lengths, bitrates))
+}
+
+pub fn get_tags(video: &Model) -> Vec<String> {
+    video.tags.clone().map_or(Vec::new(), |tags| {
+        tags.split(",").map(str::to_string).collect()
+    })
+}
+
+pub fn video_to_document(video: &Model) -> MeilliDocument {
+    MeilliDocument {
+        id: video.uuid.to_string(),
+        value: json!({
+            "title": video.title,
+            "description": video.description,
+            "tags": get_tags(video),
+            "views": video.views,
+            "likes": video.likes,
+            "duration": video.duration,
+            "timestamp": video.timestamp,
+            "timestamp_unix": video.timestamp.and_utc().timestamp(),
+            "user_id": video.user_id,
+        }),
+    }
+}
+
+pub fn video_to_item(video: &Model) -> Item {
+    let mut labels: Vec<String> = get_tags(video)
+        .iter()
+        .map(|tag| format!("tag:{tag}"))
+        .collect();
+
+    labels.extend_from_slice(&[
+        format!("title:{}", video.title),
+        format!("duration:{}", video.duration),
+        format!("channel:{}", video.user_id),
+        length_label(video.duration),
+        format!("framerate:{}", video.framerate),
+        format!("has_audio:{}", video.has_audio),
+    ]);
+
+    if let Some(resolution) = get_resolutions(video, PartialEq::eq, VideoUploadState::Available)
+        .into_iter()
+        .max()
+    {
+        labels.push(format!("resolution:{resolution}"));
+    }
+
+    Item {
+        item_id: video.uuid.to_string(),
+        is_hidden: video.hidden,
+        labels,
+        categories: Vec::new(),
+        timestamp: video.timestamp.to_string(),
+        comment: video.description.clone().unwrap_or_default(),
+    }
+}
+
 pub fn resolution_to_column(resolution: u16) -> actix_web::Result<video::Column> {
     match resolution {
         144 => Ok(video::Column::State144p),
@@ -148,7 +294,7 @@ pub fn resolution_to_column(resolution: u16) -> actix_web::Result<video::Column>
 }
 
 pub fn valid_resolution(resolution: u16) -> Result<(), ValidationError> {
-    let resolutions = vec![144, 240, 360, 480, 720, 1080, 1440];
+    let resolutions = [144, 240, 360, 480, 720, 1080, 1440];
 
     if resolutions.contains(&resolution) {
         Ok(())
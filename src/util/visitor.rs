@@ -0,0 +1,221 @@
+use std::{future::Future, time::Duration};
+
+use actix_web::{
+    cookie::{time, Cookie, CookieJar, Key, SameSite},
+    dev::{Service, ServiceRequest, ServiceResponse},
+    web::Data,
+    HttpMessage, HttpRequest,
+};
+use chrono::Utc;
+use fred::{
+    interfaces::{KeysInterface, SortedSetsInterface},
+    types::{Expiration, RedisValue, SetOptions},
+};
+use gorse_rs::Feedback;
+use uuid::Uuid;
+
+use crate::AppState;
+
+const VISITOR_COOKIE: &str = "visitor";
+const VISITOR_COOKIE_MAX_AGE_DAYS: i64 = 365;
+/// Last activity of every visitor, scored by unix timestamp.
+const VISITORS_REDIS_KEY: &str = "visitors";
+/// An inactive visitor is removed from Gorse after this many days.
+const VISITOR_RETENTION_DAYS: i64 = 30;
+const VISITOR_CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+const FEEDBACK_TYPES: [&str; 6] = ["open", "display", "view", "read", "like", "share"];
+
+/// Anonymous visitor identity, taken from the signed cookie.
+#[derive(Clone, Copy, Debug)]
+pub struct VisitorId(pub Uuid);
+
+impl VisitorId {
+    pub fn gorse_user_id(&self) -> String {
+        format!("visitor:{}", self.0)
+    }
+}
+
+fn read_visitor_cookie(request: &ServiceRequest, key: &Key) -> Option<Uuid> {
+    let mut jar = CookieJar::new();
+
+    jar.add_original(request.cookie(VISITOR_COOKIE)?);
+
+    Uuid::parse_str(jar.signed(key).get(VISITOR_COOKIE)?.value()).ok()
+}
+
+fn visitor_cookie(uuid: &Uuid, key: &Key) -> Option<Cookie<'static>> {
+    let mut jar = CookieJar::new();
+
+    jar.signed_mut(key).add(
+        Cookie::build(VISITOR_COOKIE, uuid.to_string())
+            .path("/")
+            .http_only(true)
+            .secure(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::days(VISITOR_COOKIE_MAX_AGE_DAYS))
+            .finish(),
+    );
+
+    jar.delta().next().cloned()
+}
+
+/// Static files and media are cached for a year, a cookie set on them would be shared and go stale.
+fn mints_visitor_id(path: &str) -> bool {
+    let is_media =
+        path.starts_with("/thumbnail/") || path.starts_with("/video/") || path.ends_with("/banner");
+    let is_static = path
+        .rsplit('/')
+        .next()
+        .is_some_and(|segment| segment.contains('.'));
+
+    !is_media && !is_static
+}
+
+/// Gives every anonymous visitor a stable id, signed so it can't be picked by the client.
+pub fn visitor_middleware<S, B>(
+    request: ServiceRequest,
+    service: &S,
+) -> impl Future<Output = actix_web::Result<ServiceResponse<B>>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    let key = request
+        .app_data::<Data<AppState<'static>>>()
+        .map(|data| data.visitor_key.clone());
+    let mut new_cookie = None;
+
+    let assigned = request.extensions().get::<VisitorId>().copied();
+
+    if let (Some(key), None) = (&key, assigned) {
+        // minted even with a __session cookie, it may be expired or signed out
+        let uuid = read_visitor_cookie(&request, key).or_else(|| {
+            mints_visitor_id(request.path()).then(|| {
+                let uuid = Uuid::new_v4();
+
+                new_cookie = visitor_cookie(&uuid, key);
+
+                uuid
+            })
+        });
+
+        if let Some(uuid) = uuid {
+            request.extensions_mut().insert(VisitorId(uuid));
+        }
+    }
+
+    let response = service.call(request);
+
+    async move {
+        let mut response = response.await?;
+
+        if let Some(cookie) = new_cookie {
+            response.response_mut().add_cookie(&cookie).ok();
+        }
+
+        Ok(response)
+    }
+}
+
+pub fn get_visitor_id(request: &HttpRequest) -> Option<VisitorId> {
+    request.extensions().get::<VisitorId>().copied()
+}
+
+pub async fn touch_visitor(visitor_id: &VisitorId, data: &AppState<'_>) {
+    data.redis_client
+        .zadd::<RedisValue, _, _>(
+            VISITORS_REDIS_KEY,
+            None,
+            None,
+            false,
+            false,
+            (Utc::now().timestamp() as f64, visitor_id.gorse_user_id()),
+        )
+        .await
+        .ok();
+}
+
+/// Copies the anonymous feedback to the logged in user, once per visitor and user.
+pub async fn merge_visitor(visitor_id: &VisitorId, user_id: &str, data: &AppState<'_>) {
+    let visitor_user_id = visitor_id.gorse_user_id();
+    let first_merge = data
+        .redis_client
+        .set::<Option<String>, _, _>(
+            format!("visitor_merged:{}:{user_id}", visitor_id.0),
+            Utc::now().timestamp(),
+            Some(Expiration::EX(VISITOR_RETENTION_DAYS * 86400)),
+            Some(SetOptions::NX),
+            false,
+        )
+        .await
+        .is_ok_and(|reply| reply.is_some());
+
+    if !first_merge || data.gorse_breaker.is_open() {
+        return;
+    }
+
+    let mut feedback = Vec::new();
+
+    for feedback_type in FEEDBACK_TYPES {
+        if let Ok(visitor_feedback) = data
+            .gorse_client
+            .list_feedback(&visitor_user_id, feedback_type)
+            .await
+        {
+            feedback.extend(
+                visitor_feedback
+                    .into_iter()
+                    .map(|visitor_feedback| Feedback {
+                        user_id: user_id.to_string(),
+                        ..visitor_feedback
+                    }),
+            );
+        }
+    }
+
+    if !feedback.is_empty() {
+        data.gorse_client.insert_feedback(&feedback).await.ok();
+    }
+}
+
+async fn delete_inactive_visitors(data: &AppState<'_>) -> anyhow::Result<()> {
+    let max_timestamp = Utc::now().timestamp() - VISITOR_RETENTION_DAYS * 86400;
+    let visitor_user_ids = data
+        .redis_client
+        .zrangebyscore::<Vec<String>, _, _, _>(
+            VISITORS_REDIS_KEY,
+            "-inf",
+            max_timestamp as f64,
+            false,
+            None,
+        )
+        .await?;
+
+    for visitor_user_id in visitor_user_ids {
+        if data
+            .gorse_client
+            .delete_user(&visitor_user_id)
+            .await
+            .is_ok()
+        {
+            data.redis_client
+                .zrem::<RedisValue, _, _>(VISITORS_REDIS_KEY, visitor_user_id)
+                .await
+                .ok();
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the Gorse users of the visitors not seen for the retention period.
+pub fn spawn_visitor_cleanup_job(data: Data<AppState<'static>>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(VISITOR_CLEANUP_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            delete_inactive_visitors(&data).await.ok();
+        }
+    });
+}
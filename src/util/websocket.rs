@@ -0,0 +1,88 @@
+use actix_codec::{Decoder, Encoder};
+use actix_http::ws::{handshake, Codec, Frame, Message, ProtocolError};
+use actix_web::{
+    body::{BodyStream, BoxBody},
+    web::{Bytes, BytesMut, Payload},
+    HttpRequest, HttpResponse,
+};
+use futures::StreamExt;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+const WEBSOCKET_MAX_FRAME_SIZE: usize = 65536;
+
+/// Sending half of a WebSocket, the connection closes once every copy is dropped.
+#[derive(Clone)]
+pub struct WebSocket {
+    sender: UnboundedSender<Message>,
+}
+
+impl WebSocket {
+    /// Returns false once the connection is gone.
+    pub fn text(&self, text: impl Into<String>) -> bool {
+        self.sender.send(Message::Text(text.into().into())).is_ok()
+    }
+
+    pub fn ping(&self) -> bool {
+        self.sender.send(Message::Ping(Bytes::new())).is_ok()
+    }
+
+    pub fn pong(&self, bytes: Bytes) -> bool {
+        self.sender.send(Message::Pong(bytes)).is_ok()
+    }
+
+    pub fn close(&self) {
+        self.sender.send(Message::Close(None)).ok();
+    }
+}
+
+/// Receiving half of a WebSocket.
+pub struct WebSocketStream {
+    payload: Payload,
+    buffer: BytesMut,
+    codec: Codec,
+}
+
+impl WebSocketStream {
+    /// Next frame sent by the client, `None` once the connection is closed.
+    pub async fn recv(&mut self) -> Option<Result<Frame, ProtocolError>> {
+        loop {
+            match self.codec.decode(&mut self.buffer) {
+                Ok(Some(frame)) => return Some(Ok(frame)),
+                Ok(None) => {}
+                Err(error) => return Some(Err(error)),
+            }
+
+            match self.payload.next().await? {
+                Ok(bytes) => self.buffer.extend_from_slice(&bytes),
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+/// Upgrades the request, the response must be returned by the handler for the connection to open.
+pub fn start(
+    request: &HttpRequest,
+    payload: Payload,
+) -> actix_web::Result<(HttpResponse, WebSocket, WebSocketStream)> {
+    let mut response = handshake(request.head())?;
+    let (sender, receiver) = unbounded_channel();
+    let mut codec = Codec::new().max_size(WEBSOCKET_MAX_FRAME_SIZE);
+    let messages = UnboundedReceiverStream::new(receiver).map(move |message| {
+        let mut buffer = BytesMut::new();
+
+        codec.encode(message, &mut buffer).map(|_| buffer.freeze())
+    });
+    let response = response.message_body(BoxBody::new(BodyStream::new(messages)))?;
+
+    Ok((
+        HttpResponse::from(response),
+        WebSocket { sender },
+        WebSocketStream {
+            payload,
+            buffer: BytesMut::new(),
+            codec: Codec::new().max_size(WEBSOCKET_MAX_FRAME_SIZE),
+        },
+    ))
+}
@@ -0,0 +1,257 @@
+import { formatCount } from "/js/utils/count.mjs"
+
+const UNREAD_REFRESH_INTERVAL = 60000
+
+class NotificationButtonElement extends HTMLElement {
+    #button = document.createElement("button")
+    #badge = document.createElement("span")
+    #menu = document.createElement("div")
+    #list = document.createElement("div")
+    #more = document.createElement("button")
+    #page = -1
+
+    constructor() {
+        super()
+    }
+
+    async connectedCallback() {
+        const seletor = `notification-button[data-id="${this.dataset.id = Date.now()}"]`
+        const style = document.createElement("style")
+
+        style.textContent += /*css*/`
+            ${seletor} {
+                display: inline-block;
+
+                position: relative;
+            }
+
+            ${seletor}>button {
+                position: relative;
+            }
+
+            ${seletor} .badge {
+                position: absolute;
+                top: -.25em;
+                right: -.25em;
+
+                padding: 0 .35em;
+
+                min-width: 1.25em;
+
+                font-size: .75em;
+                line-height: 1.25em;
+                text-align: center;
+
+                border-radius: .625em;
+
+                color: rgb(var(--color-fixed-dark));
+                background-color: rgb(var(--color-primary));
+            }
+
+            ${seletor} .menu {
+                position: absolute;
+                top: 2.75em;
+                right: 0;
+
+                width: 22em;
+                max-height: 70vh;
+
+                border: solid .1em rgb(var(--color-dark) / .1);
+                border-radius: .5em;
+
+                overflow-y: auto;
+
+                background-color: color-mix(in srgb, rgb(var(--color-dark) / .1) 50%, rgb(var(--color-light) / .9));
+
+                backdrop-filter: blur(10px);
+            }
+
+            ${seletor} .menu>button {
+                width: 100%;
+
+                border: 0;
+                border-radius: 0;
+            }
+
+            ${seletor} .notification {
+                display: flex;
+                flex-direction: column;
+                gap: .25em;
+
+                padding: .5em .75em;
+
+                text-decoration: none;
+
+                color: inherit;
+            }
+
+            ${seletor} .notification:not(.read) {
+                background-color: rgb(var(--color-dark) / .05);
+            }
+
+            ${seletor} .notification p {
+                margin: 0;
+
+                overflow: hidden;
+
+                text-overflow: ellipsis;
+            }
+
+            ${seletor} .notification time, ${seletor} .empty {
+                font-size: .85em;
+
+                opacity: .75;
+            }
+
+            ${seletor} .empty {
+                margin: .75em;
+            }
+        `
+
+        await Clerk.load()
+
+        if (!Clerk.user)
+            return
+
+        this.#button.ariaLabel = "Notifications"
+        this.#button.innerHTML = /*html*/`<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor"><path d="M22 20H2V18H3V11.0314C3 6.04348 7.02944 2 12 2C16.9706 2 21 6.04348 21 11.0314V18H22V20ZM5 18H19V11.0314C19 7.14806 15.866 4 12 4C8.13401 4 5 7.14806 5 11.0314V18ZM9.5 21H14.5C14.5 22.3807 13.3807 23.5 12 23.5C10.6193 23.5 9.5 22.3807 9.5 21Z"></path></svg>`
+        this.#button.classList.add("icon", "rounded", "inverted")
+        this.#badge.classList.add("badge")
+        this.#badge.hidden = true
+        this.#menu.ariaLabel = "Notifications"
+        this.#menu.tabIndex = 0
+        this.#menu.hidden = true
+        this.#menu.classList.add("menu")
+        this.#more.textContent = "Voir plus"
+        this.#more.classList.add("inverted")
+        this.#more.hidden = true
+
+        const read_all = document.createElement("button")
+
+        read_all.textContent = "Tout marquer comme lu"
+        read_all.classList.add("inverted")
+        read_all.addEventListener("click", () => this.#markAsRead(null))
+        this.#more.addEventListener("click", () => this.#loadMore())
+        this.#menu.append(read_all, document.createElement("hr"), this.#list, this.#more)
+
+        let timeout = null
+
+        this.#menu.addEventListener("focusin", () => {
+            if (timeout !== null) {
+                clearTimeout(timeout)
+                timeout = null
+            }
+        })
+        this.#menu.addEventListener("focusout", () => timeout = setTimeout(() => this.#menu.hidden = true, 100))
+        this.#button.addEventListener("click", async () => {
+            if (this.#menu.hidden) {
+                this.#menu.hidden = false
+                this.#menu.focus()
+                this.#list.replaceChildren()
+                this.#page = -1
+                await this.#loadMore()
+            }
+        })
+        this.#button.append(this.#badge)
+        this.append(style, this.#button, this.#menu)
+
+        await this.#refreshUnread()
+
+        setInterval(() => this.#refreshUnread(), UNREAD_REFRESH_INTERVAL)
+    }
+
+    #updateBadge(unread) {
+        this.#badge.hidden = !unread
+        this.#badge.textContent = formatCount(unread)
+    }
+
+    async #refreshUnread() {
+        const response = await fetch("/notification/unread")
+
+        if (response.ok)
+            this.#updateBadge((await response.json()).unread)
+    }
+
+    async #loadMore() {
+        const response = await fetch(`/notification?page=${this.#page + 1}`)
+
+        if (!response.ok)
+            return
+
+        const page = await response.json()
+
+        this.#page = page.page
+        this.#more.hidden = !page.has_more
+        this.#updateBadge(page.unread)
+        this.#list.append(...page.notifications.map(notification => this.#createNotification(notification)))
+
+        if (!this.#list.children.length) {
+            const empty = document.createElement("p")
+
+            empty.textContent = "Aucune notification pour le moment."
+            empty.classList.add("empty")
+            this.#list.append(empty)
+        }
+    }
+
+    async #markAsRead(ids) {
+        const response = await fetch("/notification/read", {
+            method: "POST",
+            headers: { "content-type": "application/json" },
+            body: JSON.stringify({ ids })
+        })
+
+        if (!response.ok)
+            return
+
+        this.#updateBadge((await response.json()).unread)
+
+        for (const notification of this.#list.getElementsByClassName("notification"))
+            if (ids === null || ids.includes(+notification.dataset.id))
+                notification.classList.add("read")
+    }
+
+    #createNotification(notification) {
+        const link = document.createElement("a")
+        const text = document.createElement("p")
+        const time = document.createElement("time")
+        const username = notification.channel_info?.username ?? ""
+        const title = notification.title ?? ""
+
+        switch (notification.type) {
+            case "upload":
+                text.textContent = `${username} a mis en ligne : ${title}`
+                break
+            case "reply":
+                text.textContent = notification.comment
+                    ? `${username} a répondu à votre commentaire : ${notification.comment.body}`
+                    : `${username} a répondu à votre commentaire sur ${title}`
+                break
+            case "like_milestone":
+                text.textContent = `Votre vidéo ${title} a atteint ${formatCount(notification.value)} j'aime`
+                break
+            case "upload_failed":
+                text.textContent = `La résolution ${notification.value}p de ${title} n'a pas pu être validée`
+                break
+        }
+
+        link.href = notification.type == "upload_failed" ? "/upload" : `/watch/${notification.uuid}`
+        link.dataset.id = notification.id
+        link.classList.add("notification")
+        link.classList.toggle("read", notification.read)
+        link.addEventListener("click", async e => {
+            if (!notification.read) {
+                e.preventDefault()
+                await this.#markAsRead([notification.id])
+                location.href = link.href
+            }
+        })
+        time.dateTime = notification.timestamp
+        time.textContent = new Date(notification.timestamp).toLocaleString("fr-FR", { dateStyle: "short", timeStyle: "short" })
+        link.append(text, time)
+
+        return link
+    }
+}
+
+customElements.define("notification-button", NotificationButtonElement)
@@ -0,0 +1,123 @@
+TimeAgo.addDefaultLocale(await (await fetch("https://unpkg.com/javascript-time-ago@2.5/locale/fr.json")).json())
+
+const time_ago = new TimeAgo('fr')
+
+for (const date_element of document.getElementsByTagName("time"))
+    date_element.textContent = time_ago.format(new $mol_time_moment(date_element.dateTime).valueOf())
+
+const action = async (url, method) => {
+    const response = await fetch(url, { method })
+
+    if (!response.ok)
+        alert("Une erreur est survenue : action annuler.")
+
+    return response.ok
+}
+
+// reports
+for (const report of document.getElementById("reports").children) {
+    if (!report.dataset.id)
+        continue
+
+    report.querySelector(".hide")?.addEventListener("click", async () => {
+        if (!confirm("Retirer ce contenu ? Tous ses signalements seront clos."))
+            return
+
+        if (await action(`/admin/${report.dataset.target_type}/${report.dataset.target_id}/hide`, "POST"))
+            location.reload()
+    })
+    report.querySelector(".dismiss").addEventListener("click", async () => {
+        if (await action(`/admin/report/${report.dataset.id}`, "DELETE"))
+            report.remove()
+    })
+}
+
+// hidden videos and comments
+for (const target of document.querySelectorAll("#hidden_videos li, #hidden_comments li")) {
+    target.querySelector(".restore")?.addEventListener("click", async () => {
+        if (await action(`/admin/${target.dataset.target_type}/${target.dataset.target_id}/hide`, "DELETE"))
+            location.reload()
+    })
+}
+
+// reindex
+const reindex_button = document.getElementById("reindex_button")
+const reindex_report = document.getElementById("reindex_report")
+
+const id_list = (title, ids) => {
+    const details = document.createElement("details")
+    const summary = document.createElement("summary")
+    const list = document.createElement("code")
+
+    summary.textContent = `${title} (${ids.length})`
+    list.textContent = ids.join(" ")
+    details.append(summary, list)
+
+    return details
+}
+
+const show_reindex_status = status => {
+    const summary = document.createElement("p")
+
+    if (status.state == "running")
+        summary.textContent = "Réindexation en cours…"
+    else if (status.state == "failed")
+        summary.textContent = `La réindexation a échoué : ${status.error}`
+    else {
+        const report = status.report
+
+        summary.textContent = `${report.videos} vidéos traitées. Recherche : ${report.search_added.length} ajoutées, ${report.search_updated} mises à jour, ${report.search_removed.length} supprimées. Recommandations : ${report.recommendation_added.length} ajoutées, ${report.recommendation_updated} mises à jour, ${report.recommendation_removed.length} supprimées. Chaines : ${report.channel_added.length} ajoutées, ${report.channel_updated} mises à jour, ${report.channel_removed.length} supprimées.`
+    }
+
+    const items = [summary]
+
+    if (status.report)
+        items.push(
+            id_list("Ajoutées à la recherche", status.report.search_added),
+            id_list("Supprimées de la recherche", status.report.search_removed),
+            id_list("Ajoutées aux recommandations", status.report.recommendation_added),
+            id_list("Supprimées des recommandations", status.report.recommendation_removed),
+            id_list("Chaines ajoutées", status.report.channel_added),
+            id_list("Chaines supprimées", status.report.channel_removed),
+        )
+
+    if (status.state != "running" && !status.logged)
+        items.push("Le journal des actions n'a pas pu être écrit.")
+
+    reindex_report.replaceChildren(...items)
+    reindex_report.hidden = false
+}
+
+const poll_reindex_status = async () => {
+    const response = await fetch("/admin/reindex")
+
+    if (!response.ok)
+        return
+
+    const status = await response.json()
+
+    show_reindex_status(status)
+
+    if (status.state == "running")
+        setTimeout(poll_reindex_status, 2000)
+}
+
+reindex_button.addEventListener("click", async () => {
+    if (!confirm("Réindexer toutes les vidéos ? Cela peut prendre un moment."))
+        return
+
+    reindex_button.disabled = true
+
+    const response = await fetch("/admin/reindex", { method: "POST" })
+
+    reindex_button.disabled = false
+
+    if (response.status == 409)
+        alert("Une réindexation est déjà en cours.")
+    else if (!response.ok)
+        return alert("Une erreur est survenue : action annuler.")
+
+    poll_reindex_status()
+})
+
+poll_reindex_status()
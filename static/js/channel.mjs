@@ -0,0 +1,89 @@
+import "/component/video-preview/video-preview.mjs"
+import { formatViews } from "./utils/views.mjs"
+import { formatCount } from "./utils/count.mjs"
+import { setupSubscription } from "./subscription.mjs"
+
+TimeAgo.addDefaultLocale(await (await fetch("https://unpkg.com/javascript-time-ago@2.5/locale/fr.json")).json())
+
+const time_ago = new TimeAgo('fr')
+
+function update_video_list_item(video) {
+    const views_element = video.getElementsByClassName("views")[0]
+
+    views_element.textContent = formatViews(+views_element.dataset.views)
+
+    const date_element = video.getElementsByTagName("time")[0]
+
+    date_element.textContent = time_ago.format(new $mol_time_moment(date_element.dateTime).valueOf())
+}
+
+for (const video of document.getElementById("video_list").getElementsByTagName("video-preview"))
+    update_video_list_item(video.parentElement)
+
+// channel stats
+const video_count = document.getElementById("channel_video_count")
+const total_views = document.getElementById("channel_total_views")
+
+video_count.textContent = `${formatCount(+video_count.dataset.count)} vidéo${+video_count.dataset.count > 1 ? "s" : ""}`
+total_views.textContent = formatViews(+total_views.dataset.views)
+
+const subscribe = document.getElementById("channel_subscribe")
+const subscribers = document.getElementById("channel_subscribers")
+
+if (subscribe)
+    setupSubscription(subscribe, subscribers)
+else
+    subscribers.textContent = `${formatCount(+subscribers.dataset.subscribers)} abonné${+subscribers.dataset.subscribers > 1 ? "s" : ""}`
+
+// channel customization
+const BANNER_WIDTH = 1536
+const BANNER_HEIGHT = 256
+
+async function encodeBanner(file) {
+    const bitmap = await createImageBitmap(file)
+    const canvas = new OffscreenCanvas(BANNER_WIDTH, BANNER_HEIGHT)
+    const context = canvas.getContext("2d")
+    // crop the image to cover the banner
+    const scale = Math.max(BANNER_WIDTH / bitmap.width, BANNER_HEIGHT / bitmap.height)
+    const width = bitmap.width * scale
+    const height = bitmap.height * scale
+
+    context.drawImage(bitmap, (BANNER_WIDTH - width) / 2, (BANNER_HEIGHT - height) / 2, width, height)
+
+    const blob = await canvas.convertToBlob({ type: "image/webp", quality: .8 })
+
+    return new Promise(resolve => {
+        const reader = new FileReader()
+
+        reader.addEventListener("load", () => resolve(reader.result))
+        reader.readAsDataURL(blob)
+    })
+}
+
+const edit = document.getElementById("channel_edit")
+const form = document.getElementById("channel_form")
+
+edit?.addEventListener("click", () => form.hidden = !form.hidden)
+form?.addEventListener("submit", async e => {
+    e.preventDefault()
+
+    const form_data = new FormData(form)
+    const banner_file = form_data.get("banner")
+    const body = { description: form_data.get("description") }
+
+    if (form_data.get("remove_banner"))
+        body.banner = ""
+    else if (banner_file?.size)
+        body.banner = await encodeBanner(banner_file)
+
+    const response = await fetch("/channel", {
+        method: "PUT",
+        headers: { "content-type": "application/json" },
+        body: JSON.stringify(body)
+    })
+
+    if (response.ok)
+        location.reload()
+    else
+        alert("Une erreur est survenue : modification annulée.")
+})
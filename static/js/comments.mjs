@@ -0,0 +1,308 @@
+import { formatCount } from "./utils/count.mjs"
+import { report } from "./utils/report.mjs"
+
+export class Comments {
+    #uuid
+    #user_id
+    #owner
+    #time_ago
+    #sort = "newest"
+    #count = document.getElementById("comments_count")
+    #sort_select = document.getElementById("comments_sort")
+    #form = document.getElementById("comments_form")
+    #list = document.getElementById("comments_list")
+    #more = document.getElementById("comments_more")
+
+    constructor(time_ago) {
+        const comments = document.getElementById("comments")
+
+        this.#uuid = comments.dataset.uuid
+        this.#user_id = comments.dataset.user_id
+        this.#owner = comments.dataset.owner == "true"
+        this.#time_ago = time_ago
+        this.#count.textContent = formatCount(+this.#count.dataset.count)
+
+        for (const comment of this.#list.children)
+            this.#setupComment(comment)
+
+        this.#sort_select.addEventListener("input", async () => {
+            this.#sort = this.#sort_select.value
+            this.#list.replaceChildren()
+            this.#list.dataset.page = -1
+            await this.#loadMore()
+        })
+        this.#more.addEventListener("click", () => this.#loadMore())
+        this.#form?.addEventListener("submit", async e => {
+            e.preventDefault()
+
+            const comment = await this.#send(this.#form, null)
+
+            if (comment) {
+                this.#list.prepend(comment)
+                this.#count.dataset.count = +this.#count.dataset.count + 1
+                this.#count.textContent = formatCount(+this.#count.dataset.count)
+            }
+        })
+    }
+
+    async #fetchPage(parent_id, page) {
+        const params = new URLSearchParams({ sort: this.#sort, page })
+
+        if (parent_id !== null)
+            params.set("parent_id", parent_id)
+
+        const response = await fetch(`/comment/${this.#uuid}?${params}`)
+
+        return response.ok ? await response.json() : null
+    }
+
+    async #loadMore() {
+        const page = await this.#fetchPage(null, +this.#list.dataset.page + 1)
+
+        if (page) {
+            this.#list.append(...page.comments.map(comment => this.#createComment(comment)))
+            this.#list.dataset.page = page.page
+            this.#more.hidden = !page.has_more
+        }
+    }
+
+    async #send(form, parent_id) {
+        const body = new FormData(form).get("body")
+        const response = await fetch("/comment", {
+            method: "PUT",
+            headers: { "content-type": "application/json" },
+            body: JSON.stringify({ uuid: this.#uuid, parent_id, body })
+        })
+
+        if (!response.ok) {
+            alert("Une erreur est survenue : commentaire non envoyé.")
+
+            return null
+        }
+
+        form.reset()
+
+        return this.#createComment(await response.json())
+    }
+
+    #createComment(comment) {
+        const element = document.createElement("li")
+
+        element.classList.add("comment")
+        element.dataset.id = comment.id
+        element.dataset.parent_id = comment.parent_id ?? ""
+        element.dataset.user_id = comment.channel_info.user_id
+        element.dataset.likes = comment.likes
+        element.dataset.liked = comment.liked
+        element.dataset.replies = comment.replies
+        element.dataset.deleted = comment.deleted
+        element.dataset.held = comment.held
+        element.dataset.pinned = comment.pinned
+        element.innerHTML = /*html*/`
+            <img alt="Photo de profile de la chaine" width="40" height="40" loading="lazy">
+            <div>
+                <div class="comment_head">
+                    <strong></strong>
+                    <time></time>
+                    ${comment.edited ? "<span>(modifié)</span>" : ""}
+                    ${comment.pinned ? `<span class="comment_pinned">📌 Épinglé</span>` : ""}
+                    ${comment.held ? `<span class="comment_held">En attente de validation</span>` : ""}
+                </div>
+                <p class="comment_body"></p>
+                <div class="comment_actions">
+                    <button class="comment_like inverted" aria-label="Bouton j'aime"></button>
+                    <button class="comment_reply inverted">Répondre</button>
+                    <button class="comment_edit inverted" hidden="">Modifier</button>
+                    <button class="comment_delete inverted" hidden="">Supprimer</button>
+                    <button class="comment_pin inverted" hidden=""></button>
+                    <button class="comment_approve inverted" hidden="">Approuver</button>
+                    <button class="comment_block inverted" hidden="">Bloquer</button>
+                    <button class="comment_report inverted" hidden="">Signaler</button>
+                </div>
+                <button class="comment_show_replies inverted" hidden=""></button>
+                <ul class="comment_replies"></ul>
+            </div>
+        `
+        element.querySelector("img").src = comment.channel_info.profil_picture
+        element.querySelector(".comment_head strong").textContent = comment.channel_info.username
+        element.querySelector("time").dateTime = comment.timestamp
+
+        const body = element.querySelector(".comment_body")
+
+        if (comment.deleted)
+            body.innerHTML = "<em>Commentaire supprimé</em>"
+        else
+            body.textContent = comment.body
+
+        this.#setupComment(element)
+
+        return element
+    }
+
+    #setupComment(element) {
+        const id = +element.dataset.id
+        const is_reply = !!element.dataset.parent_id
+        const time = element.querySelector("time")
+        const body = element.querySelector(".comment_body")
+        const like = element.querySelector(".comment_like")
+        const reply = element.querySelector(".comment_reply")
+        const edit = element.querySelector(".comment_edit")
+        const remove = element.querySelector(".comment_delete")
+        const pin = element.querySelector(".comment_pin")
+        const approve = element.querySelector(".comment_approve")
+        const block = element.querySelector(".comment_block")
+        const report_button = element.querySelector(".comment_report")
+        const show_replies = element.querySelector(".comment_show_replies")
+        const replies = element.querySelector(".comment_replies")
+        const is_author = this.#user_id && element.dataset.user_id == this.#user_id
+        const deleted = element.dataset.deleted == "true"
+        let replies_page = -1
+
+        time.textContent = this.#time_ago.format(new $mol_time_moment(time.dateTime).valueOf())
+
+        const update_like = () => like.textContent = `${element.dataset.liked == "true" ? "♥" : "♡"} ${formatCount(+element.dataset.likes)}`
+
+        update_like()
+        like.disabled = deleted || !this.#user_id
+        reply.hidden = deleted || !this.#user_id
+        edit.hidden = deleted || !is_author
+        remove.hidden = deleted || !(is_author || this.#owner)
+        pin.hidden = deleted || is_reply || !this.#owner || element.dataset.held == "true"
+        pin.textContent = element.dataset.pinned == "true" ? "Désépingler" : "Épingler"
+        approve.hidden = deleted || !this.#owner || element.dataset.held != "true"
+        block.hidden = !this.#owner || is_author
+        report_button.hidden = deleted || !this.#user_id || is_author
+
+        report_button.addEventListener("click", async () => {
+            if (await report("comment", id))
+                report_button.hidden = true
+        })
+
+        pin.addEventListener("click", async () => {
+            const response = await fetch(`/comment/${id}/pin`, { method: element.dataset.pinned == "true" ? "DELETE" : "POST" })
+
+            if (response.ok)
+                location.reload()
+            else
+                alert("Une erreur est survenue : action annuler.")
+        })
+
+        approve.addEventListener("click", async () => {
+            const response = await fetch(`/comment/${id}/approve`, { method: "POST" })
+
+            if (response.ok) {
+                element.dataset.held = false
+                approve.hidden = true
+                pin.hidden = is_reply
+                element.querySelector(".comment_held")?.remove()
+            } else
+                alert("Une erreur est survenue : action annuler.")
+        })
+
+        block.addEventListener("click", async () => {
+            if (!confirm("Bloquer cet utilisateur sur toutes vos vidéos ?"))
+                return
+
+            const response = await fetch(`/moderation/block/${element.dataset.user_id}`, { method: "PUT" })
+
+            if (response.ok)
+                block.hidden = true
+            else
+                alert("Une erreur est survenue : action annuler.")
+        })
+
+        like.addEventListener("click", async () => {
+            const response = await fetch(`/comment/${id}/like`, { method: element.dataset.liked == "true" ? "delete" : "post" })
+
+            if (response.ok) {
+                const json = await response.json()
+
+                element.dataset.likes = json.likes
+                element.dataset.liked = json.liked
+                update_like()
+            }
+        })
+
+        reply.addEventListener("click", () => {
+            // replies are kept one level deep, like the server does
+            const target = is_reply ? element.parentElement : replies
+            const form = document.createElement("form")
+
+            form.classList.add("comment_form")
+            form.innerHTML = /*html*/`
+                <textarea name="body" minlength="1" maxlength="5000" rows="1" placeholder="Ajouter une réponse..." required></textarea>
+                <input class="important" type="submit" value="Répondre">
+            `
+            form.addEventListener("submit", async e => {
+                e.preventDefault()
+
+                const comment = await this.#send(form, id)
+
+                if (comment)
+                    form.replaceWith(comment)
+            })
+            target.append(form)
+            form.querySelector("textarea").focus()
+        })
+
+        edit.addEventListener("click", () => {
+            const form = document.createElement("form")
+            const textarea = document.createElement("textarea")
+
+            form.classList.add("comment_form")
+            textarea.name = "body"
+            textarea.maxLength = 5000
+            textarea.required = true
+            textarea.value = body.textContent
+            form.append(textarea)
+            form.insertAdjacentHTML("beforeend", `<input class="important" type="submit" value="Enregistrer">`)
+            form.addEventListener("submit", async e => {
+                e.preventDefault()
+
+                const response = await fetch(`/comment/${id}`, {
+                    method: "PATCH",
+                    headers: { "content-type": "application/json" },
+                    body: JSON.stringify({ body: textarea.value })
+                })
+
+                if (response.ok) {
+                    body.textContent = (await response.json()).body
+                    form.replaceWith(body)
+                } else
+                    alert("Une erreur est survenue : modification annulée.")
+            })
+            body.replaceWith(form)
+        })
+
+        remove.addEventListener("click", async () => {
+            if (!confirm("Supprimer ce commentaire ?"))
+                return
+
+            const response = await fetch(`/comment/${id}`, { method: "DELETE" })
+
+            if (response.ok) {
+                body.innerHTML = "<em>Commentaire supprimé</em>"
+                element.dataset.deleted = true
+                like.disabled = true
+                reply.hidden = edit.hidden = remove.hidden = pin.hidden = approve.hidden = report_button.hidden = true
+            } else
+                alert("Une erreur est survenue : suppression annulée.")
+        })
+
+        const update_show_replies = has_more => {
+            show_replies.hidden = !has_more
+            show_replies.textContent = replies_page < 0 ? `Afficher ${element.dataset.replies} réponse${+element.dataset.replies > 1 ? "s" : ""}` : "Afficher plus de réponses"
+        }
+
+        update_show_replies(!is_reply && +element.dataset.replies > 0)
+        show_replies.addEventListener("click", async () => {
+            const page = await this.#fetchPage(id, replies_page + 1)
+
+            if (page) {
+                replies.append(...page.comments.map(comment => this.#createComment(comment)))
+                replies_page = page.page
+                update_show_replies(page.has_more)
+            }
+        })
+    }
+}
@@ -0,0 +1,58 @@
+import "/component/video-preview/video-preview.mjs"
+import { formatViews } from "./utils/views.mjs"
+
+TimeAgo.addDefaultLocale(await (await fetch("https://unpkg.com/javascript-time-ago@2.5/locale/fr.json")).json())
+
+const time_ago = new TimeAgo('fr')
+const video_list = document.getElementById("history_videos")
+
+for (const video of video_list.getElementsByTagName("li")) {
+    const views_element = video.getElementsByClassName("views")[0]
+
+    views_element.textContent = formatViews(+views_element.dataset.views)
+
+    const date_element = video.getElementsByTagName("time")[0]
+
+    date_element.textContent = time_ago.format(new $mol_time_moment(date_element.dateTime).valueOf())
+
+    video.getElementsByClassName("remove")[0].addEventListener("click", async () => {
+        const response = await fetch(`/history/${video.dataset.uuid}`, { method: "DELETE" })
+
+        if (response.ok)
+            video.remove()
+        else
+            alert("Une erreur est survenue : action annuler.")
+    })
+}
+
+// history settings
+const pause = document.getElementById("history_pause")
+const paused = document.getElementById("history_paused")
+
+pause.addEventListener("click", async () => {
+    const response = await fetch("/history/pause", {
+        method: "PUT",
+        headers: { "content-type": "application/json" },
+        body: JSON.stringify({ paused: pause.dataset.paused != "true" })
+    })
+
+    if (!response.ok)
+        return alert("Une erreur est survenue : action annuler.")
+
+    const json = await response.json()
+
+    pause.dataset.paused = json.paused
+    pause.textContent = json.paused ? "Reprendre l'historique" : "Suspendre l'historique"
+    paused.hidden = !json.paused
+})
+document.getElementById("history_clear").addEventListener("click", async () => {
+    if (!confirm("Effacer tout l'historique des vidéos regardées ?"))
+        return
+
+    const response = await fetch("/history", { method: "DELETE" })
+
+    if (response.ok)
+        location.reload()
+    else
+        alert("Une erreur est survenue : action annuler.")
+})
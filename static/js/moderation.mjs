@@ -0,0 +1,73 @@
+TimeAgo.addDefaultLocale(await (await fetch("https://unpkg.com/javascript-time-ago@2.5/locale/fr.json")).json())
+
+const time_ago = new TimeAgo('fr')
+
+// held comments
+for (const comment of document.getElementById("held_comments").children) {
+    if (!comment.dataset.id)
+        continue
+
+    const date_element = comment.getElementsByTagName("time")[0]
+
+    date_element.textContent = time_ago.format(new $mol_time_moment(date_element.dateTime).valueOf())
+
+    const action = async (url, method) => {
+        const response = await fetch(url, { method })
+
+        if (response.ok)
+            comment.remove()
+        else
+            alert("Une erreur est survenue : action annuler.")
+    }
+
+    comment.querySelector(".approve").addEventListener("click", () => action(`/comment/${comment.dataset.id}/approve`, "POST"))
+    comment.querySelector(".delete").addEventListener("click", () => action(`/comment/${comment.dataset.id}`, "DELETE"))
+    comment.querySelector(".block").addEventListener("click", async () => {
+        if (confirm("Bloquer cet utilisateur sur toutes vos vidéos ?"))
+            await action(`/moderation/block/${comment.dataset.user_id}`, "PUT")
+    })
+}
+
+// banned words
+const banned_words_form = document.getElementById("banned_words_form")
+
+banned_words_form.addEventListener("submit", async e => {
+    e.preventDefault()
+
+    const response = await fetch("/moderation/banned-words", {
+        method: "PUT",
+        headers: { "content-type": "application/json" },
+        body: JSON.stringify({ banned_words: new FormData(banned_words_form).get("banned_words") })
+    })
+
+    if (!response.ok)
+        alert("Une erreur est survenue : action annuler.")
+})
+
+// blocked users
+for (const user of document.getElementById("blocked_users").children) {
+    user.querySelector(".unblock")?.addEventListener("click", async () => {
+        const response = await fetch(`/moderation/block/${user.dataset.user_id}`, { method: "DELETE" })
+
+        if (response.ok)
+            user.remove()
+        else
+            alert("Une erreur est survenue : action annuler.")
+    })
+}
+
+// comments per video
+for (const checkbox of document.querySelectorAll("#video_comments input[type=checkbox]")) {
+    checkbox.addEventListener("input", async () => {
+        const response = await fetch(`/moderation/${checkbox.dataset.uuid}`, {
+            method: "PATCH",
+            headers: { "content-type": "application/json" },
+            body: JSON.stringify({ comments_disabled: checkbox.checked })
+        })
+
+        if (!response.ok) {
+            checkbox.checked = !checkbox.checked
+            alert("Une erreur est survenue : action annuler.")
+        }
+    })
+}
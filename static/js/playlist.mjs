@@ -0,0 +1,121 @@
+import "/component/video-preview/video-preview.mjs"
+import { formatViews } from "./utils/views.mjs"
+import { formatCount } from "./utils/count.mjs"
+
+TimeAgo.addDefaultLocale(await (await fetch("https://unpkg.com/javascript-time-ago@2.5/locale/fr.json")).json())
+
+const time_ago = new TimeAgo('fr')
+const playlist = document.getElementById("playlist")
+const video_list = document.getElementById("playlist_videos")
+const video_count = document.getElementById("playlist_video_count")
+const update_time = document.getElementById("playlist_update_time")
+
+function update_video_count(count) {
+    video_count.dataset.count = count
+    video_count.textContent = `${formatCount(count)} vidéo${count > 1 ? "s" : ""}`
+}
+
+update_video_count(+video_count.dataset.count)
+update_time.textContent = time_ago.format(new $mol_time_moment(update_time.dateTime).valueOf())
+
+for (const video of video_list.getElementsByTagName("li")) {
+    const views_element = video.getElementsByClassName("views")[0]
+
+    views_element.textContent = formatViews(+views_element.dataset.views)
+
+    const date_element = video.getElementsByTagName("time")[0]
+
+    date_element.textContent = time_ago.format(new $mol_time_moment(date_element.dateTime).valueOf())
+}
+
+// playlist management
+const edit = document.getElementById("playlist_edit")
+const remove = document.getElementById("playlist_delete")
+const form = document.getElementById("playlist_form")
+
+edit?.addEventListener("click", () => form.hidden = !form.hidden)
+form?.addEventListener("submit", async e => {
+    e.preventDefault()
+
+    const form_data = new FormData(form)
+    const response = await fetch(`/playlist/${playlist.dataset.id}`, {
+        method: "PATCH",
+        headers: { "content-type": "application/json" },
+        body: JSON.stringify({
+            title: form_data.get("title"),
+            description: form_data.get("description"),
+            visibility: form_data.get("visibility"),
+        })
+    })
+
+    if (response.ok)
+        location.reload()
+    else
+        alert("Une erreur est survenue : modification annulée.")
+})
+remove?.addEventListener("click", async () => {
+    if (!confirm("Supprimer définitivement cette playlist ?"))
+        return
+
+    const response = await fetch(`/playlist/${playlist.dataset.id}`, { method: "DELETE" })
+
+    if (response.ok)
+        location.href = "/"
+    else
+        alert("Une erreur est survenue : action annuler.")
+})
+
+if (video_list.dataset.owner == "true") {
+    for (const video of video_list.getElementsByTagName("li")) {
+        video.getElementsByClassName("remove")[0].addEventListener("click", async () => {
+            const response = await fetch(`/playlist/${playlist.dataset.id}/video/${video.dataset.uuid}`, { method: "DELETE" })
+
+            if (response.ok) {
+                video.remove()
+                update_video_count(+video_count.dataset.count - 1)
+            } else
+                alert("Une erreur est survenue : action annuler.")
+        })
+    }
+
+    // drag to reorder
+    let dragged_video = null
+
+    video_list.addEventListener("dragstart", e => {
+        dragged_video = e.target.closest("li")
+        dragged_video?.classList.add("dragging")
+    })
+    video_list.addEventListener("dragover", e => {
+        const video = e.target.closest("li")
+
+        if (!dragged_video || !video || video == dragged_video)
+            return
+
+        e.preventDefault()
+
+        const rect = video.getBoundingClientRect()
+
+        if (e.clientY > rect.top + rect.height / 2)
+            video.after(dragged_video)
+        else
+            video.before(dragged_video)
+    })
+    video_list.addEventListener("dragend", async () => {
+        if (!dragged_video)
+            return
+
+        dragged_video.classList.remove("dragging")
+        dragged_video = null
+
+        const response = await fetch(`/playlist/${playlist.dataset.id}/order`, {
+            method: "PUT",
+            headers: { "content-type": "application/json" },
+            body: JSON.stringify({ uuids: [...video_list.getElementsByTagName("li")].map(video => video.dataset.uuid) })
+        })
+
+        if (!response.ok) {
+            alert("Une erreur est survenue : action annuler.")
+            location.reload()
+        }
+    })
+}
@@ -1,5 +1,6 @@
 import "/component/video-preview/video-preview.mjs"
 import { formatViews } from "./utils/views.mjs"
+import { formatCount } from "./utils/count.mjs"
 
 TimeAgo.addDefaultLocale(await (await fetch("https://unpkg.com/javascript-time-ago@2.5/locale/fr.json")).json())
 
@@ -19,4 +20,19 @@ const video_list_element = document.getElementById("video_list")
 
 for (const video of video_list_element.children) {
     update_video_list_item(video)
-}
\ No newline at end of file
+}
+for (const subscribers of document.getElementsByClassName("subscribers"))
+    subscribers.textContent = `${formatCount(+subscribers.dataset.subscribers)} abonné${+subscribers.dataset.subscribers > 1 ? "s" : ""}`
+for (const video_count of document.getElementsByClassName("video_count"))
+    video_count.textContent = `${formatCount(+video_count.dataset.count)} vidéo${+video_count.dataset.count > 1 ? "s" : ""}`
+
+// filters
+const filters = document.getElementById("results_filters")
+
+for (const select of filters.getElementsByTagName("select"))
+    select.addEventListener("change", () => filters.requestSubmit())
+
+document.getElementById("results_channel_remove")?.addEventListener("click", () => {
+    filters.elements.namedItem("channel").remove()
+    filters.requestSubmit()
+})
@@ -0,0 +1,28 @@
+import { formatCount } from "./utils/count.mjs"
+
+export function setupSubscription(button, subscribers) {
+    const update = () => {
+        const subscribed = button.dataset.subscribed == "true"
+        const count = +subscribers.dataset.subscribers
+
+        button.textContent = subscribed ? "Abonné" : "S'abonner"
+        button.classList.toggle("important", !subscribed)
+        subscribers.textContent = `${formatCount(count)} abonné${count > 1 ? "s" : ""}`
+    }
+
+    update()
+    button.addEventListener("click", async () => {
+        const response = await fetch(`/subscription/${button.dataset.channel_id}`, { method: button.dataset.subscribed == "true" ? "DELETE" : "POST" })
+
+        if (response.ok) {
+            const json = await response.json()
+
+            button.dataset.subscribed = json.subscribed
+            subscribers.dataset.subscribers = json.subscribers
+            update()
+        } else if (response.status == 401)
+            alert("Vous devez être connecté pour vous abonner.")
+        else
+            alert("Une erreur est survenue : action annuler.")
+    })
+}
@@ -1,28 +1,452 @@
 import "/component/video-player/video-player.mjs"
+import { VideoSource } from "./video-source.mjs"
 
-// watch together
-const video_player_element = document.querySelector("video-player")
-const video_player = video_player_element.getPlayer({ live: true })
-const peer = new Peer()
+// past this drift the player jumps to the room position
+const MAX_DRIFT = 0.5
+const PING_INTERVAL = 10_000
+const MAX_MESSAGES = 200
 
-peer.on("open", () => {
-    const connection = peer.connect(video_player_element.dataset.uuid)
+// voice channel, a mesh of peer connections signaled through the room socket
+class Voice {
+    #send
+    #button
+    #stream = null
+    #connections = new Map()
 
-    connection.on("open", () => {
-        connection.on("data", data => {
-            video_player.poster = URL.createObjectURL(new Blob([data], { type: "image/webp" }))
+    constructor(button, send) {
+        this.#button = button
+        this.#send = send
+
+        this.#button.addEventListener("click", () => this.#stream ? this.leave() : this.join())
+    }
+
+    async join() {
+        try {
+            this.#stream = await navigator.mediaDevices.getUserMedia({ audio: true })
+        } catch {
+            return alert("Une erreur est survenue : impossible d'accéder au micro.")
+        }
+
+        this.#button.textContent = "Quitter le vocal"
+        this.#button.classList.add("active")
+        this.#send({ type: "voice", enabled: true })
+    }
+
+    leave() {
+        this.#stream?.getTracks().forEach(track => track.stop())
+        this.#stream = null
+        this.#closeAll()
+        this.#button.textContent = "Rejoindre le vocal"
+        this.#button.classList.remove("active")
+        this.#send({ type: "voice", enabled: false })
+    }
+
+    // a new connection gets a new member id, so the peers start over
+    reconnected() {
+        this.#closeAll()
+
+        if (this.#stream)
+            this.#send({ type: "voice", enabled: true })
+    }
+
+    // the member with the lowest id makes the offer, so both sides never offer at once
+    update(members, member_id) {
+        const peers = new Set(this.#stream && members.some(member => member.id == member_id && member.voice)
+            ? members.filter(member => member.voice && member.id != member_id).map(member => member.id)
+            : [])
+
+        for (const peer_id of this.#connections.keys())
+            if (!peers.has(peer_id))
+                this.#close(peer_id)
+
+        for (const peer_id of peers)
+            if (!this.#connections.has(peer_id)) {
+                this.#connect(peer_id)
+
+                if (member_id < peer_id)
+                    this.#offer(peer_id)
+            }
+    }
+
+    async signal(from, signal) {
+        if (!this.#stream)
+            return
+
+        const { connection } = this.#connections.get(from) ?? this.#connect(from)
+
+        if (signal.description) {
+            await connection.setRemoteDescription(signal.description)
+
+            if (signal.description.type == "offer") {
+                await connection.setLocalDescription()
+                this.#send({ type: "signal", to: from, signal: { description: connection.localDescription } })
+            }
+        } else if (signal.candidate) {
+            await connection.addIceCandidate(signal.candidate).catch(() => { })
+        }
+    }
+
+    #connect(peer_id) {
+        const connection = new RTCPeerConnection({ iceServers: video_metadata.ice_servers.map(urls => ({ urls })) })
+        const audio = new Audio()
+        const peer = { connection, audio }
+
+        this.#stream.getTracks().forEach(track => connection.addTrack(track, this.#stream))
+        connection.addEventListener("icecandidate", e => {
+            if (e.candidate)
+                this.#send({ type: "signal", to: peer_id, signal: { candidate: e.candidate } })
+        })
+        connection.addEventListener("track", e => {
+            audio.srcObject = e.streams[0]
+            audio.play().catch(() => { })
+        })
+        this.#connections.set(peer_id, peer)
+
+        return peer
+    }
+
+    async #offer(peer_id) {
+        const { connection } = this.#connections.get(peer_id)
+
+        await connection.setLocalDescription()
+        this.#send({ type: "signal", to: peer_id, signal: { description: connection.localDescription } })
+    }
+
+    #close(peer_id) {
+        const { connection, audio } = this.#connections.get(peer_id)
+
+        connection.close()
+        audio.srcObject = null
+        this.#connections.delete(peer_id)
+    }
+
+    #closeAll() {
+        for (const peer_id of [...this.#connections.keys()])
+            this.#close(peer_id)
+    }
+}
+
+// room chat and reactions, the messages only reach the page through the socket
+class Chat {
+    #send
+    #messages = document.getElementById("together_messages")
+    #overlay = document.getElementById("together_reaction_overlay")
+    #form = document.getElementById("together_chat_form")
+    #input = document.getElementById("together_chat_input")
+
+    constructor(send) {
+        this.#send = send
+
+        this.#form.addEventListener("submit", e => {
+            e.preventDefault()
+
+            const text = this.#input.value.trim()
+
+            if (text.length) {
+                this.#send({ type: "chat", text })
+                this.#input.value = ""
+            }
+        })
+
+        for (const button of document.getElementById("together_reactions").children)
+            button.addEventListener("click", () => this.#send({ type: "reaction", emoji: button.dataset.emoji }))
+    }
+
+    set muted(muted) {
+        this.#input.disabled = muted
+        this.#input.placeholder = muted ? "L'hôte vous a rendu muet" : "Envoyer un message"
+    }
+
+    history(messages) {
+        this.#messages.replaceChildren(...messages.map(message => this.#renderMessage(message)))
+        this.#messages.scrollTop = this.#messages.scrollHeight
+    }
+
+    add(message) {
+        const at_bottom = this.#messages.scrollHeight - this.#messages.scrollTop - this.#messages.clientHeight < 32
+
+        this.#messages.append(this.#renderMessage(message))
+
+        while (this.#messages.children.length > MAX_MESSAGES)
+            this.#messages.firstElementChild.remove()
+
+        if (at_bottom)
+            this.#messages.scrollTop = this.#messages.scrollHeight
+    }
+
+    notice(text) {
+        const item = document.createElement("li")
+
+        item.className = "notice"
+        item.textContent = text
+        this.#messages.append(item)
+        this.#messages.scrollTop = this.#messages.scrollHeight
+    }
+
+    react(reaction) {
+        const emoji = document.createElement("span")
+
+        emoji.textContent = reaction.emoji
+        emoji.title = reaction.username
+        emoji.style.left = `${10 + Math.random() * 80}%`
+        emoji.addEventListener("animationend", () => emoji.remove())
+        this.#overlay.append(emoji)
+    }
+
+    #renderMessage(message) {
+        const item = document.createElement("li")
+        const content = document.createElement("div")
+        const username = document.createElement("strong")
+        const time = document.createElement("time")
+        const text = document.createElement("p")
+        const date = new Date(message.timestamp)
+
+        if (message.profil_picture) {
+            const picture = document.createElement("img")
+
+            picture.src = message.profil_picture
+            picture.alt = ""
+            picture.width = 24
+            picture.height = 24
+            item.append(picture)
+        }
+
+        username.textContent = message.username
+        time.dateTime = date.toISOString()
+        time.textContent = date.toLocaleTimeString("fr-FR", { hour: "2-digit", minute: "2-digit" })
+        text.textContent = message.text
+        content.append(username, " ", time, text)
+        item.append(content)
+
+        return item
+    }
+}
+
+class Room {
+    #video_player
+    #socket = null
+    #member_id = null
+    #state = null
+    #status = document.getElementById("together_status")
+    #members = document.getElementById("together_members")
+    // server time minus local time, from the ping with the shortest round trip
+    #clock_offset = 0
+    #best_round_trip = Infinity
+    #retry_delay = 1_000
+    #voice = null
+    #chat = new Chat(message => this.#send(message))
+    #kicked = false
+
+    constructor(video_player) {
+        this.#video_player = video_player
+
+        const voice_button = document.getElementById("together_voice")
+
+        if (voice_button)
+            this.#voice = new Voice(voice_button, message => this.#send(message))
+
+        this.#video_player.addEventListener("play", () => this.#onLocalChange())
+        this.#video_player.addEventListener("pause", () => this.#onLocalChange())
+        this.#video_player.addEventListener("seeked", () => this.#onLocalChange())
+        this.#video_player.addEventListener("ratechange", () => this.#onLocalChange())
+        this.#video_player.addEventListener("timeupdate", () => this.#correctDrift())
+        document.getElementById("together_copy").addEventListener("click", () => navigator.clipboard.writeText(location.href))
+        setInterval(() => this.#send({ type: "ping", client_time: Date.now() }), PING_INTERVAL)
+
+        this.#connect()
+    }
+
+    get serverTime() {
+        return Date.now() + this.#clock_offset
+    }
+
+    get expectedPosition() {
+        if (this.#state.paused)
+            return this.#state.position
+
+        return this.#state.position + (this.serverTime - this.#state.updated_at) / 1_000 * this.#state.rate
+    }
+
+    #connect() {
+        this.#socket = new WebSocket(`${location.protocol == "https:" ? "wss" : "ws"}://${location.host}/together/${video_metadata.room}/ws`)
+        this.#socket.addEventListener("open", () => {
+            this.#retry_delay = 1_000
+            this.#best_round_trip = Infinity
+            this.#status.textContent = "Connecté"
+            this.#status.dataset.connected = true
+
+            // a few pings right away give a first clock offset
+            for (let i = 0; i < 5; i++)
+                setTimeout(() => this.#send({ type: "ping", client_time: Date.now() }), i * 200)
         })
-    })
+        this.#socket.addEventListener("message", e => this.#onMessage(JSON.parse(e.data)))
+        this.#socket.addEventListener("close", () => {
+            if (this.#kicked)
+                return
+
+            this.#status.textContent = "Reconnexion..."
+            this.#status.dataset.connected = false
+
+            setTimeout(() => this.#connect(), this.#retry_delay)
+
+            this.#retry_delay = Math.min(this.#retry_delay * 2, 10_000)
+        })
+    }
+
+    #send(message) {
+        if (this.#socket?.readyState === WebSocket.OPEN)
+            this.#socket.send(JSON.stringify(message))
+    }
+
+    #onMessage(message) {
+        switch (message.type) {
+            case "welcome":
+                this.#member_id = message.member_id
+                this.#voice?.reconnected()
+                break
+            case "pong": {
+                const now = Date.now()
+                const round_trip = now - message.client_time
+
+                if (round_trip <= this.#best_round_trip) {
+                    this.#best_round_trip = round_trip
+                    this.#clock_offset = message.server_time - (message.client_time + now) / 2
+                }
+                break
+            }
+            case "state":
+                this.#state = message
+                this.applyState()
+                break
+            case "members":
+                this.#renderMembers(message.members)
+                this.#voice?.update(message.members, this.#member_id)
+                break
+            case "signal":
+                this.#voice?.signal(message.from, message.signal)
+                break
+            case "history":
+                this.#chat.history(message.messages)
+                break
+            case "chat":
+                this.#chat.add(message)
+                break
+            case "reaction":
+                this.#chat.react(message)
+                break
+            case "refused":
+                this.#chat.notice(message.reason == "muted" ? "L'hôte vous a rendu muet." : "Trop de messages, patientez quelques secondes.")
+                break
+            case "kicked":
+                this.#kicked = true
+                this.#voice?.leave()
+                this.#socket.close()
+                this.#status.textContent = "Vous avez été exclu du salon"
+                this.#status.dataset.connected = false
+                break
+        }
+    }
+
+    applyState() {
+        if (!this.#state || this.#video_player.video.readyState < HTMLMediaElement.HAVE_METADATA)
+            return
+
+        this.#video_player.video.playbackRate = this.#state.rate
+
+        if (Math.abs(this.#video_player.currentTime - this.expectedPosition) > MAX_DRIFT)
+            this.#video_player.currentTime = this.expectedPosition
+
+        if (this.#state.paused != this.#video_player.paused)
+            this.#state.paused ? this.#video_player.pause() : this.#video_player.play().catch(() => { })
+    }
+
+    #correctDrift() {
+        if (this.#state && !this.#state.paused && !this.#video_player.paused && Math.abs(this.#video_player.currentTime - this.expectedPosition) > MAX_DRIFT)
+            this.#video_player.currentTime = this.expectedPosition
+    }
+
+    // changes matching the room state come from the room itself, anything else is sent,
+    // the server applies it for the host and answers with the current state otherwise
+    #onLocalChange() {
+        if (!this.#state)
+            return
+
+        const position = this.#video_player.currentTime
+
+        if (this.#video_player.video.playbackRate != this.#state.rate)
+            this.#send({ type: "rate", rate: this.#video_player.video.playbackRate })
+        else if (this.#video_player.paused != this.#state.paused)
+            this.#send({ type: this.#video_player.paused ? "pause" : "play", position })
+        else if (Math.abs(position - this.expectedPosition) > MAX_DRIFT)
+            this.#send({ type: "seek", position })
+    }
+
+    #renderMembers(members) {
+        const self = members.find(member => member.id == this.#member_id)
+
+        this.#chat.muted = self?.muted ?? false
+        this.#members.replaceChildren(...members.map(member => {
+            const item = document.createElement("li")
+            const name = document.createElement("span")
+
+            if (member.profil_picture) {
+                const picture = document.createElement("img")
+
+                picture.src = member.profil_picture
+                picture.alt = ""
+                picture.width = 32
+                picture.height = 32
+                item.append(picture)
+            }
+
+            name.textContent = member.username
+            item.append(name)
+            item.dataset.host = member.host
+            item.dataset.voice = member.voice
+            item.dataset.muted = member.muted
+            item.dataset.self = member.id == this.#member_id
+
+            // moderation, the server checks the host again
+            if (self?.host && !member.host) {
+                const mute = document.createElement("button")
+                const kick = document.createElement("button")
+
+                mute.className = kick.className = "rounded"
+                mute.textContent = member.muted ? "Rétablir" : "Rendre muet"
+                mute.addEventListener("click", () => this.#send({ type: "mute", member_id: member.id, muted: !member.muted }))
+                kick.textContent = "Exclure"
+                kick.addEventListener("click", () => {
+                    if (confirm(`Exclure ${member.username} du salon ?`))
+                        this.#send({ type: "kick", member_id: member.id })
+                })
+                item.append(mute, kick)
+            }
+
+            return item
+        }))
+    }
+}
+
+const video_player = document.querySelector("video-player").getPlayer(Object.assign({
+    fullscreen_element: document.getElementById("main_container"),
+    start_time: 0,
+}, video_metadata))
+
+const response = await fetch(`/thumbnail/${video_metadata.uuid}`)
+const t0 = Date.now()
+const data = await response.blob()
+const t1 = Date.now()
 
-    const canvas = document.createElement("canvas")
+video_player.poster = URL.createObjectURL(data)
 
-    canvas.width = 0
-    canvas.height = 0
+const video_source = new VideoSource(video_player, video_metadata, data.size * 1_000 / Math.max(t1 - t0, 1))
+const room = new Room(video_player)
 
-    const stream = canvas.captureStream(1)
-    const call = peer.call(video_player_element.dataset.uuid, stream)
+video_player.addEventListener("loadedmetadata", () => room.applyState())
 
-    stream.removeTrack(stream.getVideoTracks()[0])
+video_player.src = URL.createObjectURL(video_source)
+video_player.preview = `/video/${video_metadata.uuid}/${video_metadata.resolutions[0]}`
 
-    call.on("stream", stream => video_player.srcObject = stream)
-})
\ No newline at end of file
+window.video_source = video_source
+window.video_player = video_player
+window.room = room
@@ -58,6 +58,34 @@ for (const video of video_list_element.children) {
     video_list_item_element_add_event(video)
 }
 
+// live upload state
+function formatBytes(bytes) {
+    if (bytes < 1_000_000)
+        return `${Math.round(bytes / 1_000)} ko`
+
+    return `${Math.round(bytes / 100_000) / 10} Mo`
+}
+
+const upload_events = new EventSource("/upload/events")
+
+upload_events.addEventListener("message", e => {
+    const event = JSON.parse(e.data)
+    const resolution_element = video_list_element.querySelector(`button[data-uuid="${event.uuid}"] .bottom [data-resolution="${event.resolution}"]`)
+
+    if (!resolution_element)
+        return
+
+    switch (event.type) {
+        case "progress":
+            resolution_element.textContent = `${event.resolution}p ⚠️ ${formatBytes(event.bytes)}`
+            break
+        case "state":
+            resolution_element.textContent = `${event.resolution}p ${event.state == "available" ? "✅" : "🚫"}`
+            resolution_element.title = event.error ?? ""
+            break
+    }
+})
+
 // video upload form
 const header_element = document.getElementsByTagName("header")[0]
 
@@ -189,14 +217,13 @@ video_upload_form_element.addEventListener("submit", async e => {
     video_list_element.prepend(video_list_item_element)
 
     const resolutions = [144, 240, 360, 480, 720, 1080, 1440]
-    const resolution_elements = {}
 
     for (const resolution of resolutions) {
         const resolution_element = document.createElement("span")
         const is_encoding_resolution = video_encode_options_list.find(video_encode_options => video_encode_options.resolution == resolution) !== undefined
 
+        resolution_element.dataset.resolution = resolution
         resolution_element.textContent = `${resolution}p ${is_encoding_resolution ? "⚠️" : "🚫"}`
-        resolution_elements[resolution] = resolution_element
         bottom_element.append(resolution_element)
     }
 
@@ -259,7 +286,6 @@ video_upload_form_element.addEventListener("submit", async e => {
                 video_uuid
             }
 
-            resolution_elements[e.resolution].textContent = `${e.resolution}p ✅`
             pushToQueue(e.resolution, chunk)
         }, 200)
     })
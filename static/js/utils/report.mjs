@@ -0,0 +1,21 @@
+export async function report(target_type, target_id) {
+    const reason = prompt("Pourquoi signalez-vous ce contenu ?")?.trim()
+
+    if (!reason)
+        return false
+
+    const response = await fetch("/report", {
+        method: "POST",
+        headers: { "content-type": "application/json" },
+        body: JSON.stringify({ target_type, target_id: target_id.toString(), reason: reason.slice(0, 1000) })
+    })
+
+    if (response.ok)
+        alert("Merci, le signalement a été transmis à l'équipe de modération.")
+    else if (response.status == 401)
+        alert("Vous devez être connecté pour signaler un contenu.")
+    else
+        alert("Une erreur est survenue : signalement non envoyé.")
+
+    return response.ok
+}
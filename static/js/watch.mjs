@@ -1,8 +1,12 @@
 import "/component/video-player/video-player.mjs"
+import "/component/video-preview/video-preview.mjs"
 import { formatViews } from "./utils/views.mjs"
 import { formatDuration } from "./utils/duration.mjs"
 import { formatCount } from "./utils/count.mjs"
 import { VideoSource } from "./video-source.mjs"
+import { Comments } from "./comments.mjs"
+import { report } from "./utils/report.mjs"
+import { setupSubscription } from "./subscription.mjs"
 
 TimeAgo.addDefaultLocale(await (await fetch("https://unpkg.com/javascript-time-ago@2.5/locale/fr.json")).json())
 
@@ -14,6 +18,7 @@ class VideoInfo {
     #likes = document.getElementById("video_info_likes")
     #watch_together = document.getElementById("video_info_watch_together")
     #share = document.getElementById("video_info_share")
+    #report = document.getElementById("video_info_report")
     #share_dialog = {
         dialog: document.getElementById("video_info_share_dialog"),
         close: document.getElementById("video_info_share_close"),
@@ -40,14 +45,13 @@ class VideoInfo {
 
         this.#show_more.addEventListener("click", () => this.showMore = !this.showMore)
         this.#likes.addEventListener("click", async () => {
-            if (this.liked) {
-                await this.removeLike()
-                this.likes -= 1
-                this.liked = false
-            } else {
-                await this.addLike()
-                this.likes += 1
-                this.liked = true
+            const response = await (this.liked ? this.removeLike() : this.addLike())
+
+            if (response.ok) {
+                const json = await response.json()
+
+                this.likes = json.likes
+                this.liked = json.liked
             }
         })
 
@@ -61,42 +65,18 @@ class VideoInfo {
             }
         }
 
-        let watch_together_url = ""
-
-        this.#watch_together.addEventListener("click", () => {
-            if (!watch_together_url.length) {
-                const peer = new Peer()
-                const stream = video_player.captureStream()
-                const canvas = new OffscreenCanvas(video_player.videoWidth, video_player.videoHeight)
-                const context = canvas.getContext("2d")
-
-                sendShareFeedback()
-
-                if ("mozCaptureStream" in HTMLMediaElement.prototype) {
-                    const audio_context = new AudioContext()
-                    const media_stream_source = audio_context.createMediaStreamSource(stream)
+        this.#watch_together.addEventListener("click", async () => {
+            const response = await fetch("/together", {
+                method: "post",
+                headers: { "Content-Type": "application/json" },
+                body: JSON.stringify({ video: video_metadata.uuid, position: video_player.currentTime }),
+            })
 
-                    media_stream_source.connect(audio_context.destination)
-                }
-
-                peer.on("open", id => {
-                    this.#watch_together.children[0].style.display = "none"
-                    this.#watch_together.children[1].style.display = "block"
-                    watch_together_url = `${location.origin}/together/${id}`
-
-                    navigator.clipboard.writeText(watch_together_url)
-                })
-                peer.on("connection", connection => {
-                    connection.on("open", async () => {
-                        context.drawImage(video_player.video, 0, 0)
-
-                        connection.send(await canvas.convertToBlob({ type: "image/webp", quality: 0.85 }))
-                    })
-                })
-                peer.on("call", call => call.answer(stream))
-            }
+            if (!response.ok)
+                return alert("Une erreur est survenue : impossible de créer le salon.")
 
-            navigator.clipboard.writeText(watch_together_url)
+            sendShareFeedback()
+            location.href = `/together/${(await response.json()).room}`
         })
 
         const update_link = () => this.#share_dialog.link.value = `${location.origin}/watch/${video_metadata.uuid}${this.#share_dialog.start_at.checked ? `&t=${video_player.currentTime}` : ""}`
@@ -117,6 +97,8 @@ class VideoInfo {
                 close_dialog()
         })
         this.#share_dialog.copy.addEventListener("click", () => navigator.clipboard.writeText(this.#share_dialog.link.value))
+        this.#report.addEventListener("click", () => report("video", video_metadata.uuid))
+        setupSubscription(document.getElementById("video_info_subscribe"), document.getElementById("video_info_subscribers"))
     }
 
     async addLike() {
@@ -190,10 +172,22 @@ class VideoInfo {
 
 // load and manage video stream
 const video_player = document.querySelector("video-player").getPlayer(Object.assign({
-    fullscreen_element: document.getElementById("main_container")
+    fullscreen_element: document.getElementById("main_container"),
+    // a shared start time wins over the resume position
+    start_time: new URLSearchParams(location.search).has("t") ? undefined : video_metadata.resume_at ?? undefined,
 }, video_metadata))
 
 window.video_info = new VideoInfo(video_metadata, video_player)
+window.comments = new Comments(time_ago)
+
+// related videos
+for (const video of document.getElementById("related_list")?.children ?? []) {
+    const views_element = video.getElementsByClassName("views")[0]
+    const date_element = video.getElementsByTagName("time")[0]
+
+    views_element.textContent = formatViews(+views_element.dataset.views)
+    date_element.textContent = time_ago.format(new $mol_time_moment(date_element.dateTime).valueOf())
+}
 
 const response = await fetch(`/thumbnail/${video_metadata.uuid}`)
 const t0 = Date.now()
@@ -210,4 +204,109 @@ video_player.src = URL.createObjectURL(video_source)
 video_player.preview = `/video/${video_metadata.uuid}/${video_metadata.resolutions[0]}`
 
 window.video_source = video_source
-window.video_player = video_player
\ No newline at end of file
+window.video_player = video_player
+// watch history progress
+if (video_metadata.logged_in) {
+    let last_position = null
+    const sendProgress = () => {
+        const position = video_player.currentTime
+
+        if (position === last_position || isNaN(position))
+            return
+
+        last_position = position
+        fetch(`/history/${video_metadata.uuid}`, {
+            method: "POST",
+            headers: { "content-type": "application/json" },
+            body: JSON.stringify({ position }),
+            keepalive: true
+        })
+    }
+
+    setInterval(() => video_player.paused || sendProgress(), 15_000)
+    video_player.video.addEventListener("pause", sendProgress)
+    video_player.video.addEventListener("ended", sendProgress)
+    addEventListener("pagehide", sendProgress)
+}
+
+// save to a playlist
+const save = document.getElementById("video_info_save")
+
+if (save) {
+    const save_dialog = document.getElementById("video_info_save_dialog")
+    const save_list = document.getElementById("video_info_save_list")
+    const save_form = document.getElementById("video_info_save_form")
+    const close_dialog = () => save_dialog.close()
+
+    function addPlaylist(playlist) {
+        const item = document.createElement("li")
+        const label = document.createElement("label")
+        const checkbox = document.createElement("input")
+
+        checkbox.type = "checkbox"
+        checkbox.checked = playlist.contains
+        checkbox.addEventListener("input", async () => {
+            const response = await fetch(`/playlist/${playlist.id}/video/${video_metadata.uuid}`, { method: checkbox.checked ? "PUT" : "DELETE" })
+
+            if (!response.ok) {
+                checkbox.checked = !checkbox.checked
+                alert("Une erreur est survenue : action annuler.")
+            }
+        })
+        label.append(checkbox, ` ${playlist.title}`)
+        item.append(label)
+        save_list.append(item)
+    }
+
+    save.addEventListener("click", async () => {
+        const response = await fetch(`/playlist?uuid=${video_metadata.uuid}`)
+
+        if (!response.ok)
+            return alert("Une erreur est survenue : action annuler.")
+
+        save_list.replaceChildren()
+        ;(await response.json()).forEach(addPlaylist)
+        save_dialog.showModal()
+    })
+    document.getElementById("video_info_save_close").addEventListener("click", close_dialog)
+    save_dialog.addEventListener("click", e => {
+        const rect = save_dialog.getBoundingClientRect()
+
+        if (e.clientY < rect.top || e.clientY > rect.bottom || e.clientX < rect.left || e.clientX > rect.right)
+            close_dialog()
+    })
+    save_form.addEventListener("submit", async e => {
+        e.preventDefault()
+
+        const form_data = new FormData(save_form)
+        const playlist = {
+            title: form_data.get("title"),
+            visibility: form_data.get("visibility"),
+        }
+        const response = await fetch("/playlist", {
+            method: "PUT",
+            headers: { "content-type": "application/json" },
+            body: JSON.stringify(Object.assign({ uuid: video_metadata.uuid }, playlist))
+        })
+
+        if (!response.ok)
+            return alert("Une erreur est survenue : action annuler.")
+
+        addPlaylist(Object.assign({ id: await response.text(), contains: true }, playlist))
+        save_form.reset()
+    })
+}
+
+// playlist autoplay
+const playlist_panel = document.getElementById("playlist_panel")
+
+if (playlist_panel) {
+    const autoplay = document.getElementById("playlist_autoplay")
+
+    autoplay.checked = localStorage.getItem("playlist_autoplay") !== "false"
+    autoplay.addEventListener("input", () => localStorage.setItem("playlist_autoplay", autoplay.checked))
+    video_player.video.addEventListener("ended", () => {
+        if (autoplay.checked && playlist_panel.dataset.next)
+            location.href = `/watch/${playlist_panel.dataset.next}?list=${playlist_panel.dataset.id}`
+    })
+}